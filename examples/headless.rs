@@ -0,0 +1,80 @@
+// Runs a ROM without a window and prints the display, using only the core library.
+// cargo run --example headless [--max-instructions N] [--max-seconds S] [--keys FILE] [romfile]
+// Stops early once the ROM halts in an idle loop, and exits with status 2 if it times out.
+// With --keys it runs one frame per line of key states instead (see keystream.rs for the format),
+// reading them from a file, a named pipe, or stdin if FILE is "-".
+// With --seed it makes a golden run instead: a fixed seed, the --keys script (if any) and exactly
+// --max-instructions instructions, printing the final state checksum to compare against a golden.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::time::Duration;
+use chip8_rust::chip8::{Chip8, RunOutcome};
+use chip8_rust::keystream::KeyStream;
+
+// Draws "8" in the middle of the screen, then idles
+const DEMO_ROM: [u8; 12] = [0x60, 0x08, 0xF0, 0x29, 0x61, 0x1E, 0x62, 0x0D, 0xD1, 0x25, 0x12, 0x0A];
+const DEFAULT_MAX_INSTRUCTIONS: u64 = 1_000_000;
+const DEFAULT_MAX_SECONDS: f64 = 10.;
+const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+fn main() {
+    let mut max_instructions = DEFAULT_MAX_INSTRUCTIONS;
+    let mut max_seconds = DEFAULT_MAX_SECONDS;
+    let mut keys_file = None;
+    let mut seed = None;
+    let mut romfile = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-instructions" => max_instructions = args.next().and_then(|n| n.parse().ok()).expect("--max-instructions needs a number"),
+            "--max-seconds" => max_seconds = args.next().and_then(|s| s.parse().ok()).expect("--max-seconds needs a number"),
+            "--keys" => keys_file = Some(args.next().expect("--keys needs a file, or - for stdin")),
+            "--seed" => seed = Some(args.next().and_then(|n| n.parse().ok()).expect("--seed needs a number")),
+            _ => romfile = Some(arg),
+        }
+    }
+    let rom = match romfile {
+        Some(filename) => std::fs::read(&filename).expect("Unable to read ROM"),
+        None => DEMO_ROM.to_vec(),
+    };
+
+    let key_stream = keys_file.map(|keys_file| {
+        let reader: Box<dyn BufRead> = match keys_file.as_str() {
+            "-" => Box::new(io::stdin().lock()),
+            filename => Box::new(BufReader::new(File::open(filename).expect("Unable to open key file"))),
+        };
+        KeyStream::new(reader)
+    });
+    if let Some(seed) = seed {
+        let frames = key_stream.into_iter().flatten().map(|keys| keys.expect("Bad key input"));
+        let checksum = Chip8::golden_run(&rom, seed, frames, INSTRUCTIONS_PER_FRAME, max_instructions).expect("Unable to load ROM");
+        println!("{:016x}", checksum);
+        return;
+    }
+
+    let mut chip8 = Chip8::new();
+    chip8.load_bytes(&rom).expect("Unable to load ROM");
+    if let Some(key_stream) = key_stream {
+        let mut frames = 0;
+        for keys in key_stream {
+            chip8.run_frame(&keys.expect("Bad key input"), INSTRUCTIONS_PER_FRAME);
+            frames += 1;
+        }
+        println!("Ran {} frames, PC {:03X}", frames, chip8.pc);
+        println!("{}", chip8.display_to_string());
+        return;
+    }
+    let outcome = chip8.run_bounded(max_instructions, Some(Duration::from_secs_f64(max_seconds)));
+
+    let frame = chip8.frame();
+    match outcome {
+        RunOutcome::Halted { instructions } => println!("Halted after {} instructions, PC {:03X}", instructions, chip8.pc),
+        RunOutcome::TimedOut { instructions } => println!("Timed out after {} instructions, PC {:03X}", instructions, chip8.pc),
+    }
+    println!("{}x{}", frame.width, frame.height);
+    println!("{}", chip8.display_to_string());
+    if let RunOutcome::TimedOut { .. } = outcome {
+        std::process::exit(2);
+    }
+}
@@ -0,0 +1,20 @@
+// Prints ROMs as Rust or C arrays, for baking them into a binary.
+// cargo run --example rom_to_array [--c] romfile...
+
+use chip8_rust::embed::{array_name, rom_to_array, ArrayFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let format = if args.iter().any(|arg| arg == "--c") { ArrayFormat::C } else { ArrayFormat::Rust };
+    let files: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if files.is_empty() {
+        eprintln!("Usage: rom_to_array [--c] romfile...");
+        std::process::exit(1);
+    }
+    for filename in files {
+        match std::fs::read(filename) {
+            Ok(bytes) => println!("// {}\n{}", filename, rom_to_array(&array_name(filename), &bytes, format)),
+            Err(e) => eprintln!("Unable to read '{}': {}", filename, e),
+        }
+    }
+}
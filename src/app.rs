@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::ops::Range;
 use std::time;
 use std::time::Instant;
 use macroquad::prelude::*;
@@ -5,15 +7,176 @@ use egui::{Context, Slider, Ui};
 use egui::Color32;
 use egui::RichText;
 use crate::Chip8;
+use crate::chip8::{byte_pixels, fnv1a, opcodes, DirtyRect, PcPolicy, ResetRng, Snapshot, DISPLAY_SIZE, FNV_OFFSET, PC_HISTORY_LEN};
+use crate::chip8::stack;
+use crate::chip8::error::Chip8Error;
+use crate::chip8::quirks::{self, LoadStore, Profile, Quirks};
+use crate::audio::Beeper;
+use crate::calltree::{CallNode, CallTree};
+use crate::config::{self, Config};
+use crate::console::{self, Console};
+use crate::hooks::{self, OpcodeBreak, PcAction, PcHook};
+use crate::input::{KeyInput, KeyRepeat, KeypadLayout};
+use crate::logbuffer;
+use crate::recording::{self, AudioRecorder, InputRecorder};
+use crate::pacer::{FrameClock, Pacer, Platform, PLATFORMS};
+use crate::profile::SettingsProfile;
+use crate::report::Report;
+use crate::timeline::Timeline;
+use crate::tracer::Tracer;
+use crate::watcher::FileWatcher;
+use crate::window;
+use log::{error, info};
 use rfd::FileDialog;
 use crate::miniquad::date::now;
 
+const MIN_CONTRAST_RATIO: f32 = 3.0;
+const DIFF_FADE_FRAMES: u8 = 2;
+const COLLISION_FLASH_FRAMES: u8 = 20;
+const DISASSEMBLY_LINES: usize = 16;
+const MEMORY_ROWS: usize = 8;
+const BITMAP_ROWS: usize = 64;
+const BITMAP_SCALE: f32 = 3.;
+const SAVE_STATE_SLOTS: usize = 4;
+// Widest border around the display, in low-res pixels
+const MAX_DISPLAY_BORDER: u32 = 8;
+// Stepping to the next draw gives up after this many instructions, so a ROM that never draws can't hang
+const STEP_TO_DRAW_LIMIT: u32 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayScaling {
+    Stretch,
+    PixelPerfect,
+}
+
+impl DisplayScaling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayScaling::Stretch => "Stretch",
+            DisplayScaling::PixelPerfect => "PixelPerfect",
+        }
+    }
+}
+
+// Clockwise turn of the display as it's drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Quarter, Rotation::Half, Rotation::ThreeQuarters];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rotation::None => "0°",
+            Rotation::Quarter => "90°",
+            Rotation::Half => "180°",
+            Rotation::ThreeQuarters => "270°",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rotation> {
+        Rotation::ALL.into_iter().find(|rotation| rotation.name() == name)
+    }
+}
+
+/// How the display is mirrored and then turned when it's drawn or screenshotted.
+/// The machine's own display buffer is never changed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayTransform {
+    pub rotation: Rotation,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl DisplayTransform {
+    // Width and height of a width x height display once transformed
+    pub fn size(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        match self.rotation {
+            Rotation::None | Rotation::Half => (width, height),
+            Rotation::Quarter | Rotation::ThreeQuarters => (height, width),
+        }
+    }
+
+    // Where pixel (x, y) of a width x height display is drawn
+    pub fn apply(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> (usize, usize) {
+        let x = if self.flip_x { width - 1 - x } else { x };
+        let y = if self.flip_y { height - 1 - y } else { y };
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (height - 1 - y, x),
+            Rotation::Half => (width - 1 - x, height - 1 - y),
+            Rotation::ThreeQuarters => (y, width - 1 - x),
+        }
+    }
+
+    // Where a rectangle of a width x height display is drawn. Every transform keeps its edges lined
+    // up, so two opposite corners are enough.
+    pub fn apply_rect(&self, rect: DirtyRect, dimensions: (usize, usize)) -> DirtyRect {
+        let (x1, y1) = self.apply((rect.x, rect.y), dimensions);
+        let (x2, y2) = self.apply((rect.x + rect.width - 1, rect.y + rect.height - 1), dimensions);
+        DirtyRect { x: x1.min(x2), y: y1.min(y2), width: x1.abs_diff(x2) + 1, height: y1.abs_diff(y2) + 1 }
+    }
+
+    // The display pixel drawn at (x, y), the reverse of apply
+    pub fn invert(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> (usize, usize) {
+        let (x, y) = match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (y, height - 1 - x),
+            Rotation::Half => (width - 1 - x, height - 1 - y),
+            Rotation::ThreeQuarters => (width - 1 - y, x),
+        };
+        let x = if self.flip_x { width - 1 - x } else { x };
+        let y = if self.flip_y { height - 1 - y } else { y };
+        (x, y)
+    }
+
+    // Fits the transformed display in the 64x32 unit display area, centred. Returns its bottom left
+    // corner and the size of one pixel, in the display camera's coordinates.
+    pub fn layout(&self, dimensions: (usize, usize)) -> (f32, f32, f32) {
+        let (width, height) = self.size(dimensions);
+        let size = (64. / width as f32).min(32. / height as f32);
+        ((64. - width as f32 * size) / 2., (32. - height as f32 * size) / 2., size)
+    }
+}
+
+// The look of the egui windows. Custom follows the emulator's own display colours.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiTheme {
+    Dark,
+    Light,
+    Custom,
+}
+
+impl UiTheme {
+    pub const ALL: [UiTheme; 3] = [UiTheme::Dark, UiTheme::Light, UiTheme::Custom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UiTheme::Dark => "Dark",
+            UiTheme::Light => "Light",
+            UiTheme::Custom => "Custom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<UiTheme> {
+        UiTheme::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+}
+
 pub struct App {
     pub chip8: Chip8,
     pub pause_execution: bool,
     pub step: bool,
     pub fg_color: [f32;3],
     pub bg_color: [f32;3],
+    // Flashes the display border while the sound timer runs, for when the beep can't be heard
+    pub beep_flash: bool,
+    pub beep_flash_color: [f32;3],
     bold_text_color: Color32,
     reg_read_color: Color32,
     reg_write_color: Color32,
@@ -23,18 +186,138 @@ pub struct App {
     pub draw_per_sec: u32,
     ops_last_sec: u32,
     draw_last_sec: u32,
+    // Instructions per 60Hz frame, paced against the wall clock
     pub speed: u32,
+    pub pacer: Pacer,
+    // Runs exactly 60 logical frames a second with a fixed number of instructions each, for recording
+    pub lock_60hz: bool,
+    pub frame_clock: FrameClock,
+    // Makes up time lost to stalls by running the missed instructions, rather than just running slow
+    pub catch_up: bool,
+    pub frame: u64,
+    pub run_to_frame: Option<u64>,
+    run_to_frame_input: u64,
+    pub show_display_diff: bool,
+    show_collisions: bool,
+    pub show_display_history: bool,
+    pub show_sprite_preview: bool,
+    // Whether the delay and sound timers keep counting down while execution is paused
+    pub timers_run_while_paused: bool,
+    // A second machine run alongside the first with its own ROM and quirks, drawn beside it
+    pub compare: Option<Chip8>,
+    show_compare: bool,
+    show_memory: bool,
+    show_breakpoints: bool,
+    show_memory_bitmap: bool,
+    show_opcode_status: bool,
+    show_call_tree: bool,
+    show_timeline: bool,
+    show_console: bool,
+    console: Console,
+    // Draws, sound and keys for the last few seconds of frames, each with the state to jump back to
+    timeline: Timeline,
+    // Instructions run per subroutine since the ROM was loaded
+    call_tree: CallTree,
+    bitmap_start: usize,
+    // Bytes per row, each drawn as 8 pixels
+    bitmap_width: usize,
+    pc_hooks: Vec<PcHook>,
+    save_states: [Option<Snapshot>; SAVE_STATE_SLOTS],
+    hook_pc_input: String,
+    hook_action: PcAction,
+    // Kinds of instruction to pause before, and the one picked to add next
+    opcode_breaks: Vec<OpcodeBreak>,
+    opcode_break_choice: OpcodeBreak,
+    // The PC paused at by an opcode break, so resuming runs that instruction instead of stopping again
+    opcode_break_resume: Option<usize>,
+    // Address ranges marked as data in the disassembly, per ROM hash
+    data_ranges: HashMap<u64, Vec<Range<usize>>>,
+    data_range_input: String,
+    // Range "Save memory as ROM" writes, blank for the loaded ROM's own
+    rom_range_input: String,
+    pub persist_reg_highlights: bool,
+    pub reg_highlight_frames: u8,
+    // Whether each register was last written (rather than read), and how many frames its highlight has left
+    reg_highlights: [(bool, u8); 16],
+    pub show_status_bar: bool,
+    pub show_quirks: bool,
+    pub show_opcode: bool,
+    pub show_audio_visualizer: bool,
+    pub beeper: Beeper,
+    pub rom_path: Option<String>,
+    pub input_recorder: Option<InputRecorder>,
+    pub audio_recorder: Option<AudioRecorder>,
+    pub tracer: Option<Tracer>,
+    // Whether traces include the registers and display after each draw or clear
+    trace_draw_snapshots: bool,
+    // How many recent instructions "Export instruction history" writes
+    history_export_len: usize,
+    pub reload_on_change: bool,
+    rom_watcher: Option<FileWatcher>,
+    pub hovered_pixel: Option<(usize, usize)>,
+    pub keypad_layout: KeypadLayout,
+    pub key_input: KeyInput,
+    seed_input: String,
+    frozen_random: u8,
+    // How the last "Step to next draw" went, as instructions run and whether it found a draw
+    step_to_draw_result: Option<(u32, bool)>,
+    pub display_scaling: DisplayScaling,
+    // Width of the border drawn round the display in low-res pixels, 0 for none
+    pub display_border: u32,
+    pub display_transform: DisplayTransform,
+    pub ui_theme: UiTheme,
+    config: Config,
+    input_playback: Vec<u16>,
+    prev_display: [bool; DISPLAY_SIZE],
+    display_diff: [(bool, u8); DISPLAY_SIZE],
+    // Frames left to flash each pixel a DXYN erased
+    collision_flash: [u8; DISPLAY_SIZE],
+    // Every pixel set since the ROM was loaded or the history was cleared
+    display_history: [bool; DISPLAY_SIZE],
+    // Whether each ROM's pixel history is saved when it's closed and compared against next time
+    pub compare_previous_session: bool,
+    previous_history: Option<[bool; DISPLAY_SIZE]>,
 }
 
 impl App {
     pub fn new() -> Self {
         let now = time::Instant::now();
+        let config = Config::load(config::CONFIG_FILE);
+        let keypad_layout = config
+            .get("keypad_layout")
+            .and_then(KeypadLayout::from_name)
+            .unwrap_or(KeypadLayout::Qwerty);
+        let key_repeat = config
+            .get("key_repeat")
+            .and_then(KeyRepeat::from_name)
+            .unwrap_or(KeyRepeat::Held);
+        let display_scaling = match config.get("display_scaling") {
+            Some("PixelPerfect") => DisplayScaling::PixelPerfect,
+            _ => DisplayScaling::Stretch,
+        };
+        let timers_run_while_paused = config.get("timers_run_while_paused") == Some("true");
+        let compare_previous_session = config.get("compare_previous_session") == Some("true");
+        let display_border = config
+            .get("display_border")
+            .and_then(|border| border.parse().ok())
+            .filter(|border| *border <= MAX_DISPLAY_BORDER)
+            .unwrap_or(1);
+        let display_transform = DisplayTransform {
+            rotation: config.get("display_rotation").and_then(Rotation::from_name).unwrap_or(Rotation::None),
+            flip_x: config.get("display_flip_x") == Some("true"),
+            flip_y: config.get("display_flip_y") == Some("true"),
+        };
+        let ui_theme = config.get("ui_theme").and_then(UiTheme::from_name).unwrap_or(UiTheme::Dark);
+        let mut chip8 = Chip8::new();
+        chip8.reset_rng = config.get("reset_rng").and_then(ResetRng::from_name).unwrap_or(ResetRng::Reseed);
         App {
-            chip8: Chip8::new(),
+            chip8,
             pause_execution: false,
             step: false,
             fg_color: [1.;3],
             bg_color: [0.;3],
+            beep_flash: false,
+            beep_flash_color: [1., 0.6, 0.],
             bold_text_color: Color32::from_rgb(110, 255, 110),
             reg_read_color: Color32::from_rgb(110, 110, 255),
             reg_write_color: Color32::from_rgb(255, 110, 110),
@@ -45,6 +328,421 @@ impl App {
             ops_last_sec: 0,
             draw_last_sec: 0,
             speed: 6,
+            pacer: Pacer::new(),
+            lock_60hz: false,
+            frame_clock: FrameClock::new(0.),
+            catch_up: false,
+            frame: 0,
+            run_to_frame: None,
+            run_to_frame_input: 0,
+            show_display_diff: false,
+            show_collisions: false,
+            show_display_history: false,
+            show_sprite_preview: false,
+            timers_run_while_paused,
+            compare: None,
+            show_compare: false,
+            show_memory: false,
+            show_breakpoints: false,
+            show_memory_bitmap: false,
+            show_opcode_status: false,
+            show_call_tree: false,
+            show_timeline: false,
+            show_console: false,
+            console: Console::new(),
+            timeline: Timeline::new(),
+            call_tree: CallTree::new(),
+            bitmap_start: 0x200,
+            bitmap_width: 1,
+            pc_hooks: Vec::new(),
+            save_states: Default::default(),
+            hook_pc_input: String::new(),
+            hook_action: PcAction::Pause,
+            opcode_breaks: Vec::new(),
+            opcode_break_choice: OpcodeBreak::Pattern("DXYN"),
+            opcode_break_resume: None,
+            data_ranges: HashMap::new(),
+            data_range_input: String::new(),
+            rom_range_input: String::new(),
+            persist_reg_highlights: false,
+            reg_highlight_frames: 30,
+            reg_highlights: [(false, 0); 16],
+            show_status_bar: true,
+            show_quirks: false,
+            show_opcode: false,
+            show_audio_visualizer: false,
+            beeper: Beeper::new(),
+            rom_path: None,
+            input_recorder: None,
+            audio_recorder: None,
+            tracer: None,
+            trace_draw_snapshots: false,
+            history_export_len: 32,
+            reload_on_change: false,
+            rom_watcher: None,
+            hovered_pixel: None,
+            keypad_layout,
+            key_input: KeyInput::new(key_repeat),
+            seed_input: String::new(),
+            frozen_random: 0,
+            step_to_draw_result: None,
+            display_scaling,
+            display_border,
+            display_transform,
+            ui_theme,
+            config,
+            input_playback: Vec::new(),
+            prev_display: [false; DISPLAY_SIZE],
+            display_diff: [(false, 0); DISPLAY_SIZE],
+            collision_flash: [0; DISPLAY_SIZE],
+            display_history: [false; DISPLAY_SIZE],
+            compare_previous_session,
+            previous_history: None,
+        }
+    }
+
+    pub fn load_rom(&mut self, filename: &str) {
+        // Reloading the same path after a rebuild compares the new build against the old one
+        self.save_session_history();
+        self.chip8.reset();
+        if let Err(e) = self.chip8.load_rom(filename) {
+            error!("Unable to load ROM '{}': {}", filename, e);
+        }
+        self.load_rom_settings();
+        self.call_tree.clear();
+        self.timeline.clear();
+        self.rom_path = Some(filename.to_owned());
+        self.display_history = [false; DISPLAY_SIZE];
+        self.load_previous_history();
+        self.frame = 0;
+        self.run_to_frame = None;
+        self.update_rom_watcher();
+    }
+
+    // Loads a program given as hex opcodes rather than a file
+    pub fn load_hex(&mut self, text: &str) -> Result<(), String> {
+        let program = opcodes::parse_hex_program(text)?;
+        let bytes: Vec<u8> = program.iter().flat_map(|code| code.to_be_bytes()).collect();
+        // Checked before the reset so a program that doesn't fit leaves the last one loaded
+        let space = self.chip8.rom_space();
+        if bytes.len() > space && !self.chip8.bank_switching {
+            return Err(Chip8Error::RomTooLarge { size: bytes.len(), space }.to_string());
+        }
+        self.chip8.reset();
+        self.chip8.load_bytes(&bytes).map_err(|e| e.to_string())?;
+        self.load_rom_settings();
+        self.call_tree.clear();
+        self.timeline.clear();
+        self.rom_path = None;
+        self.display_history = [false; DISPLAY_SIZE];
+        self.previous_history = None;
+        self.frame = 0;
+        self.run_to_frame = None;
+        self.update_rom_watcher();
+        Ok(())
+    }
+
+    // Starts or stops watching the ROM file to match the reload_on_change setting
+    pub fn update_rom_watcher(&mut self) {
+        self.rom_watcher = match &self.rom_path {
+            Some(path) if self.reload_on_change => Some(FileWatcher::new(path)),
+            _ => None,
+        };
+    }
+
+    pub fn poll_rom_watcher(&mut self) {
+        let changed = self.rom_watcher.as_mut().is_some_and(|watcher| watcher.poll());
+        if changed {
+            if let Some(path) = self.rom_path.clone() {
+                info!("'{}' changed on disk, reloading", path);
+                self.load_rom(&path);
+            }
+        }
+    }
+
+    // Replays or records the keys for the 60Hz frame about to run
+    pub fn begin_frame(&mut self) {
+        self.play_input_frame();
+        self.record_input_frame();
+    }
+
+    // Runs the instructions due since the last refresh spread over the 60Hz frames due by the wall
+    // clock. Timers, the frame count and input recordings only move on with whole 60Hz frames, so
+    // they keep time however fast the display refreshes, and a catch-up burst after a stall runs
+    // with the timers counting down alongside it rather than all before them.
+    pub fn run_paced(&mut self, instructions: u32, frames: u32, keypress: Option<u8>) {
+        if frames == 0 {
+            self.run_instructions(instructions, keypress);
+            return;
+        }
+        let (instructions, frames) = (instructions as u64, frames as u64);
+        for frame in 0..frames {
+            if self.pause_execution {
+                break;
+            }
+            let share = instructions * (frame + 1) / frames - instructions * frame / frames;
+            self.begin_frame();
+            // The renderer clears redraw, so this frame's draws are latched separately for the timeline
+            let pending = self.chip8.redraw;
+            self.chip8.redraw = false;
+            self.run_instructions(share as u32, keypress);
+            let drew = self.chip8.redraw;
+            self.chip8.redraw |= pending;
+            self.record_audio_frame();
+            self.tick_timers();
+            self.end_frame(drew);
+        }
+    }
+
+    // Counts a 60Hz frame, pausing if it's the one we were asked to run to
+    pub fn end_frame(&mut self, drew: bool) {
+        self.timeline.record(self.frame, drew, &self.chip8);
+        self.frame += 1;
+        for (_, frames) in self.reg_highlights.iter_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+        if self.run_to_frame.is_some_and(|target| self.frame >= target) {
+            self.run_to_frame = None;
+            self.pause_execution = true;
+        }
+    }
+
+    // Remembers the registers the last instruction touched, with writes taking priority over reads
+    pub fn track_reg_highlights(&mut self) {
+        for reg in &self.chip8.reg_read {
+            self.reg_highlights[*reg] = (false, self.reg_highlight_frames);
+        }
+        for reg in &self.chip8.reg_write {
+            self.reg_highlights[*reg] = (true, self.reg_highlight_frames);
+        }
+    }
+
+    fn reg_color(&self, reg: usize) -> Color32 {
+        if !self.persist_reg_highlights {
+            return if self.chip8.reg_read.contains(&reg) {
+                self.reg_read_color
+            } else if self.chip8.reg_write.contains(&reg) {
+                self.reg_write_color
+            } else {
+                self.bold_text_color
+            };
+        }
+        let (write, frames) = self.reg_highlights[reg];
+        let highlight = if write { self.reg_write_color } else { self.reg_read_color };
+        fade_color(self.bold_text_color, highlight, frames as f32 / self.reg_highlight_frames as f32)
+    }
+
+    pub fn run_instructions(&mut self, count: u32, keypress: Option<u8>) {
+        for _ in 0..count {
+            if self.chip8.halted {
+                self.pause_execution = true;
+                break;
+            }
+            self.tick_compare(keypress);
+            // No point running a program that's finished in an idle loop
+            if self.chip8.spinning {
+                if self.compare.as_ref().is_none_or(|compare| compare.spinning) {
+                    break;
+                }
+                continue;
+            }
+            if !self.run_instruction(keypress) || self.pause_execution {
+                break;
+            }
+        }
+    }
+
+    // Runs the next instruction unless an opcode break stops it first, then traces, profiles and fires
+    // the PC hooks. Returns whether the instruction ran.
+    fn run_instruction(&mut self, keypress: Option<u8>) -> bool {
+        if self.at_opcode_break() {
+            self.pause_execution = true;
+            return false;
+        }
+        self.chip8.tick(keypress);
+        self.track_reg_highlights();
+        self.ops_per_sec += 1;
+        self.trace_instruction();
+        self.profile_instruction();
+        self.run_pc_hooks();
+        true
+    }
+
+    // Whether the next instruction is one of the armed kinds, apart from the one just paused at
+    fn at_opcode_break(&mut self) -> bool {
+        if self.opcode_break_resume.take() == Some(self.chip8.pc) {
+            return false;
+        }
+        let code = self.chip8.next_opcode().code;
+        if self.opcode_breaks.iter().any(|opcode_break| opcode_break.matches(code)) {
+            self.opcode_break_resume = Some(self.chip8.pc);
+            return true;
+        }
+        false
+    }
+
+    // Runs the actions attached to the PC the machine has just arrived at
+    pub fn run_pc_hooks(&mut self) {
+        let actions = hooks::fire_at(&mut self.pc_hooks, self.chip8.pc);
+        for action in actions {
+            match action {
+                PcAction::Log => {
+                    let reg: Vec<String> = self.chip8.reg.iter().map(|value| format!("{:02X}", value)).collect();
+                    info!("PC {:03X}: V0-VF {} I {:03X}", self.chip8.pc, reg.join(" "), self.chip8.reg_i);
+                }
+                PcAction::SetRegister { reg, value } => {
+                    self.chip8.reg[reg] = value;
+                    self.chip8.reg_write.push(reg);
+                }
+                PcAction::Pause => self.pause_execution = true,
+                PcAction::Screenshot => self.save_screenshot(),
+                PcAction::SaveState { slot } => {
+                    self.save_states[slot] = Some(self.chip8.snapshot());
+                    info!("PC {:03X}: saved state to slot {}", self.chip8.pc, slot + 1);
+                }
+            }
+        }
+    }
+
+    pub fn save_screenshot(&self) {
+        let frame = self.chip8.frame();
+        let pixels = frame.pixels();
+        let dimensions = (frame.width, frame.height);
+        let (width, height) = self.display_transform.size(dimensions);
+        let [fg, bg] = [self.fg_color, self.bg_color].map(|[r, g, b]| Color::new(r, g, b, 1.));
+        let mut image = Image::gen_image_color(width as u16, height as u16, bg);
+        for (i, set) in pixels.iter().enumerate() {
+            if *set {
+                let (x, y) = self.display_transform.apply((i % frame.width, i / frame.width), dimensions);
+                image.set_pixel(x as u32, y as u32, fg);
+            }
+        }
+        let filename = format!("chip8_screenshot_{:03x}_{}.png", self.chip8.pc, self.frame);
+        image.export_png(&filename);
+        info!("Saved screenshot to '{}'", filename);
+    }
+
+    // Runs one instruction on the comparison machine, with the same keys held as the main one
+    pub fn tick_compare(&mut self, keypress: Option<u8>) {
+        if let Some(compare) = &mut self.compare {
+            compare.keys = self.chip8.keys;
+            if !compare.spinning {
+                compare.tick(keypress);
+            }
+        }
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.chip8.tick_timers();
+        if let Some(compare) = &mut self.compare {
+            compare.tick_timers();
+        }
+    }
+
+    // Starts a comparison machine from the start of a ROM, or the current ROM if none is given.
+    // It begins with the same quirks and seed so only what's changed afterwards differs.
+    pub fn start_compare(&mut self, filename: Option<&str>) {
+        let rom = match filename {
+            Some(filename) => match std::fs::read(filename) {
+                Ok(rom) => rom,
+                Err(e) => {
+                    error!("Unable to read ROM '{}': {}", filename, e);
+                    return;
+                }
+            },
+            None => self.chip8.rom.clone(),
+        };
+        let mut compare = Chip8::new();
+        compare.seed_rng(self.chip8.seed);
+        compare.reset();
+        compare.quirks = self.chip8.quirks;
+        if let Err(e) = compare.load_bytes(&rom) {
+            error!("Unable to load ROM to compare: {}", e);
+            return;
+        }
+        compare.redraw = true;
+        self.compare = Some(compare);
+    }
+
+    // Quirks saved for this particular ROM, since a few need non-standard behaviour to run
+    fn load_rom_settings(&mut self) {
+        let key = format!("fx0a_polling_{:016x}", self.chip8.rom_hash());
+        self.chip8.quirks.fx0a_polling = self.config.get(&key) == Some("true");
+    }
+
+    // The current ROM's data ranges, read from the config the first time it's seen
+    pub fn data_ranges(&mut self) -> &Vec<Range<usize>> {
+        let config = &self.config;
+        self.data_ranges.entry(self.chip8.rom_hash()).or_insert_with_key(|hash| {
+            config
+                .get(&format!("data_ranges_{:016x}", hash))
+                .map(opcodes::parse_data_ranges)
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn mark_data(&mut self, range: Range<usize>, is_data: bool) {
+        let hash = self.chip8.rom_hash();
+        self.data_ranges();
+        let ranges = self.data_ranges.get_mut(&hash).unwrap();
+        opcodes::mark_data(ranges, range, is_data);
+        let text = opcodes::format_data_ranges(ranges);
+        self.config.set(&format!("data_ranges_{:016x}", hash), &text);
+    }
+
+    pub fn target_ips(&self) -> u32 {
+        self.speed * 60
+    }
+
+    // Sets up the quirks and speed of a historical machine
+    pub fn select_platform(&mut self, platform: &Platform) {
+        self.chip8.quirks = Quirks::from_profile(platform.profile);
+        self.speed = (platform.ips / 60).max(1);
+    }
+
+    // Runs until an instruction changes the display, then pauses. Returns how many instructions ran
+    // and whether one of them drew, which is false if it hit the limit, a breakpoint or an idle loop first.
+    pub fn step_to_draw(&mut self, limit: u32) -> (u32, bool) {
+        // Cleared while stepping so breakpoints and pause hooks can stop it
+        self.pause_execution = false;
+        // A redraw that's already waiting isn't one we ran into, but still needs showing afterwards
+        let pending = self.chip8.redraw;
+        self.chip8.redraw = false;
+        let mut result = (0, false);
+        while result.0 < limit && !self.chip8.spinning && !self.chip8.halted {
+            self.tick_compare(None);
+            if !self.run_instruction(None) {
+                break;
+            }
+            result.0 += 1;
+            if self.chip8.redraw {
+                result.1 = true;
+                break;
+            }
+            if self.pause_execution {
+                break;
+            }
+        }
+        self.pause_execution = true;
+        self.chip8.redraw |= pending;
+        self.step_to_draw_result = Some(result);
+        result
+    }
+
+    pub fn start_run_to_frame(&mut self, target: u64) {
+        if target > self.frame {
+            self.run_to_frame = Some(target);
+            self.pause_execution = false;
+        }
+    }
+
+    pub fn rom_name(&self) -> String {
+        match &self.rom_path {
+            Some(path) => std::path::Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone()),
+            None => "No ROM".to_owned(),
         }
     }
 
@@ -59,129 +757,1794 @@ impl App {
         }
     }
 
-    pub fn label_bold(&mut self, text: &str, ui: &mut Ui) {
-        ui.label(RichText::new(text).color(self.bold_text_color));
+    // The border is only drawn around the display, so the flash never ends up in the frame itself
+    pub fn border_color(&self, chip8: &Chip8) -> Color {
+        if self.beep_flash && chip8.sound_timer > 0 {
+            let [r, g, b] = self.beep_flash_color;
+            Color::new(r, g, b, 1.)
+        } else {
+            GRAY
+        }
     }
 
-    pub fn show_main_menubar(&mut self, egui_ctx: &Context) {
-        //pub fn show_main_menubar(&mut self, egui_ctx: &Context, chip8: &mut Chip8) {
-        egui::TopBottomPanel::top("menu_bar").show(egui_ctx, |ui| {
-            egui::menu::bar(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui.button("Load ROM").clicked() {
-                        let files = FileDialog::new()
-                            .add_filter("CHIP-8 ROM", &["ch8"])
-                            .set_directory("/")
-                            .pick_file();
-                        match files {
-                            Some(path) => {
-                                let rom = path.into_os_string().into_string().unwrap();
-                                self.chip8.reset();
-                                self.chip8.load_rom(&rom);
-                            },
-                            None => ()
-                        }
-                        ui.close_menu();
-                    }
-                });
-                ui.menu_button("View", |ui| {
-                    if ui.button("Organize windows").clicked() {
-                        ui.ctx().memory().reset_areas();
-                        ui.close_menu();
-                    }
-                    if ui
-                        .button("Reset egui memory")
-                        .on_hover_text("Forget scroll, positions, sizes etc")
-                        .clicked()
-                    {
-                        *ui.ctx().memory() = Default::default();
-                        ui.close_menu();
-                    }
-                });
-            });
-        });
+    // Called once a frame while execution is paused. Timers stay frozen unless set to keep running.
+    pub fn paused_frame(&mut self, frames: u32) {
+        if self.timers_run_while_paused {
+            for _ in 0..frames {
+                self.tick_timers();
+            }
+        }
     }
 
-    pub fn show_general_state(&mut self, egui_ctx: &Context) {
-        // pub fn show_general_state(&mut self, egui_ctx: &Context, chip8: &Chip8, ops_last_sec: i32, draw_last_sec: i32) {
-        egui::Window::new("General State").show(egui_ctx, |ui| {
-            ui.set_max_width(190.);
-            self.label_bold("CPU Info:", ui);
-            ui.horizontal_wrapped(|ui| {
-                self.label_bold("PC:", ui);
-                ui.label(format!("{:02X} ", self.chip8.pc));
-                self.label_bold("OP:", ui);
-                ui.label(format!("{:02X} ", self.chip8.opcode.code));
-                self.label_bold("IR:", ui);
-                ui.label(format!("{:02X} ", self.chip8.reg_i));
-            });
-            ui.separator();
-            if !self.chip8.stack.is_empty() {
-                ui.label(format!("Stack: {:#04x}", self.chip8.stack.top()));
-            } else {
-                ui.label("Stack: empty");
+    // Starts a fade for every pixel that changed since the last frame
+    pub fn update_display_diff(&mut self) {
+        for i in 0..self.prev_display.len() {
+            let (set, frames) = &mut self.display_diff[i];
+            if self.chip8.display[i] != self.prev_display[i] {
+                *set = self.chip8.display[i];
+                *frames = DIFF_FADE_FRAMES;
+            } else if *frames > 0 {
+                *frames -= 1;
             }
-            match self.chip8.keypress {
-                Some(x) => ui.label(format!("Keypress: {:#}", x)),
-                None => ui.label("Keypress: none"),
-            };
-            ui.label(format!("Delay timer: {}", self.chip8.delay_timer));
-            ui.label(format!("Sound timer: {}", self.chip8.sound_timer));
-            ui.label(format!("Instruction/s: {}", self.ops_last_sec));
-            ui.label(format!("Redraw/s: {}", self.draw_last_sec));
-            ui.separator();
-            ui.label(RichText::new("Registers:").color(self.bold_text_color));
-            for i in (0x0..=0xF).step_by(4) {
-                ui.horizontal_wrapped(|ui| {
-                    for j in 0..4 {
-                        let reg = i + j;
-                        let mut reg_color = self.bold_text_color;
-                        if self.chip8.reg_read.contains(&reg) {
-                            reg_color = self.reg_read_color;
-                        } else if self.chip8.reg_write.contains(&reg) {
-                            reg_color = self.reg_write_color;
-                        }
-                        ui.label(RichText::new(format!("{:0X}:", reg)).color(reg_color));
-                        ui.label(format!("{:02X} ", self.chip8.reg[reg]));
-                    }
-                });
+        }
+        self.prev_display = self.chip8.display;
+    }
+
+    // Starts a flash for every pixel erased by a collision since the last frame
+    pub fn update_collisions(&mut self) {
+        for frames in self.collision_flash.iter_mut().filter(|frames| **frames > 0) {
+            *frames -= 1;
+        }
+        for offset in self.chip8.collisions.drain(..) {
+            self.collision_flash[offset] = COLLISION_FLASH_FRAMES;
+        }
+    }
+
+    pub fn draw_collisions(&self) {
+        if !self.show_collisions {
+            return;
+        }
+        let (width, height) = self.chip8.display_dimensions();
+        for (i, frames) in self.collision_flash.iter().enumerate().take(width * height) {
+            if *frames > 0 {
+                let alpha = 0.8 * *frames as f32 / COLLISION_FLASH_FRAMES as f32;
+                self.draw_display_pixel(i, Color::new(1., 0., 1., alpha));
             }
-        });
+        }
     }
 
-    pub fn show_controls(&mut self, egui_ctx: &Context) {
-        //pub fn show_controls(&mut self, egui_ctx: &Context, chip8: &mut Chip8, speed: &mut i32, pause_execution: &mut bool, step: &mut bool, fg_color: &mut [f32;3], bg_color: &mut [f32;3]) {
-        egui::Window::new("Control").show(egui_ctx, |ui| {
-            ui.set_max_width(190.);
-            ui.label(RichText::new("Execution:").color(self.bold_text_color));
-            ui.add(
-                Slider::new(&mut self.speed, 1..=30)
-                    .logarithmic(false)
-                    .text("Speed"),
-            );
-            ui.horizontal(|ui| {
-                if ui.button("Toggle execution").clicked() {
-                    self.pause_execution = !self.pause_execution;
-                }
-                if ui.button("Step").clicked() {
-                    self.step = true;
-                }
-            });
+    // Tints changed pixels on top of the display, in the display camera's coordinates
+    pub fn draw_display_diff(&self) {
+        if !self.show_display_diff {
+            return;
+        }
+        let (width, height) = self.chip8.display_dimensions();
+        for (i, (set, frames)) in self.display_diff.iter().enumerate().take(width * height) {
+            if *frames == 0 {
+                continue;
+            }
+            let alpha = 0.6 * *frames as f32 / DIFF_FADE_FRAMES as f32;
+            let color = if *set { Color::new(0.2, 1., 0.2, alpha) } else { Color::new(1., 0.2, 0.2, alpha) };
+            self.draw_display_pixel(i, color);
+        }
+    }
 
-            ui.separator();
-            ui.label(RichText::new("Display Color:").color(self.bold_text_color));
-            ui.horizontal(|ui| {
-                ui.label("FG:");
-                if ui.color_edit_button_rgb(&mut self.fg_color).changed() {
-                    self.chip8.redraw = true;
-                }
-            });
-            ui.horizontal(|ui| {
-                ui.label("BG:");
-                if ui.color_edit_button_rgb(&mut self.bg_color).changed() {
-                    self.chip8.redraw = true;
-                }
-            });
-        });
+    pub fn update_display_history(&mut self) {
+        for (seen, set) in self.display_history.iter_mut().zip(self.chip8.display) {
+            *seen |= set;
+        }
+    }
+
+    // Keyed by path rather than ROM hash, since the point is comparing different builds of one ROM
+    fn session_history_key(&self) -> Option<String> {
+        let path = self.rom_path.as_ref()?;
+        Some(format!("display_history_{:016x}", fnv1a(FNV_OFFSET, path.as_bytes())))
+    }
+
+    pub fn save_session_history(&mut self) {
+        if !self.compare_previous_session || !self.display_history.contains(&true) {
+            return;
+        }
+        if let Some(key) = self.session_history_key() {
+            let text = history_to_hex(&self.display_history);
+            self.config.set(&key, &text);
+        }
+    }
+
+    fn load_previous_history(&mut self) {
+        self.previous_history = match self.session_history_key() {
+            Some(key) if self.compare_previous_session => self.config.get(&key).and_then(history_from_hex),
+            _ => None,
+        };
+    }
+
+    // Marks pixels the last session set that this one hasn't in red, and new ones in blue
+    pub fn draw_session_diff(&self) {
+        let Some(previous) = &self.previous_history else {
+            return;
+        };
+        let (width, height) = self.chip8.display_dimensions();
+        for (i, (was_set, set)) in previous.iter().zip(self.display_history).take(width * height).enumerate() {
+            match (*was_set, set) {
+                (true, false) => self.draw_display_pixel(i, Color::new(1., 0.2, 0.2, 0.4)),
+                (false, true) => self.draw_display_pixel(i, Color::new(0.2, 0.6, 1., 0.4)),
+                _ => (),
+            }
+        }
+    }
+
+    // Tints pixels that have been set at some point but aren't now
+    pub fn draw_display_history(&self) {
+        if !self.show_display_history {
+            return;
+        }
+        let (width, height) = self.chip8.display_dimensions();
+        for i in 0..width * height {
+            if self.display_history[i] && !self.chip8.display[i] {
+                self.draw_display_pixel(i, Color::new(1., 0.8, 0.2, 0.4));
+            }
+        }
+    }
+
+    // Shows where the DXYN about to run will draw, while paused on it
+    pub fn draw_sprite_preview(&self) {
+        if !self.show_sprite_preview || !self.pause_execution {
+            return;
+        }
+        for i in self.chip8.pending_sprite() {
+            self.draw_display_pixel(i, Color::new(0.3, 0.6, 1., 0.5));
+        }
+    }
+
+    // Fills one CHIP-8 pixel, given as a display offset, in the display camera's coordinates
+    fn draw_display_pixel(&self, i: usize, color: Color) {
+        let dimensions = self.chip8.display_dimensions();
+        let (left, bottom, size) = self.display_transform.layout(dimensions);
+        let (x, y) = self.display_transform.apply((i % dimensions.0, i / dimensions.0), dimensions);
+        let height = self.display_transform.size(dimensions).1;
+        draw_rectangle(left + x as f32 * size, bottom + (height - 1 - y) as f32 * size, size, size, color);
+    }
+
+    pub fn start_input_recording(&mut self, filename: &str) {
+        self.stop_input_recording();
+        match InputRecorder::start(filename) {
+            Ok(recorder) => self.input_recorder = Some(recorder),
+            Err(e) => error!("Unable to record input to '{}': {}", filename, e),
+        }
+    }
+
+    pub fn stop_input_recording(&mut self) {
+        if let Some(recorder) = self.input_recorder.take() {
+            if let Err(e) = recorder.stop() {
+                error!("Unable to finish input recording: {}", e);
+            }
+        }
+    }
+
+    pub fn record_input_frame(&mut self) {
+        if let Some(recorder) = &mut self.input_recorder {
+            if let Err(e) = recorder.record_frame(&self.chip8.keys) {
+                error!("Unable to record input: {}", e);
+                self.input_recorder = None;
+            }
+        }
+    }
+
+    pub fn start_audio_recording(&mut self, filename: &str) {
+        self.stop_audio_recording();
+        match AudioRecorder::start(filename) {
+            Ok(recorder) => self.audio_recorder = Some(recorder),
+            Err(e) => error!("Unable to record audio to '{}': {}", filename, e),
+        }
+    }
+
+    pub fn stop_audio_recording(&mut self) {
+        if let Some(recorder) = self.audio_recorder.take() {
+            if let Err(e) = recorder.stop() {
+                error!("Unable to finish audio recording: {}", e);
+            }
+        }
+    }
+
+    // Generates the beeper output for the 60Hz frame just run and adds it to any audio recording,
+    // so a recording holds exactly one frame of samples per emulated frame
+    pub fn record_audio_frame(&mut self) {
+        self.beeper.generate_frame(self.chip8.sound_timer > 0);
+        if let Some(recorder) = &mut self.audio_recorder {
+            if let Err(e) = recorder.record_frame(&self.beeper.samples) {
+                error!("Unable to record audio: {}", e);
+                self.audio_recorder = None;
+            }
+        }
+    }
+
+    pub fn start_trace(&mut self, filename: &str) {
+        self.stop_trace();
+        match Tracer::start(filename, self.trace_draw_snapshots) {
+            Ok(tracer) => self.tracer = Some(tracer),
+            Err(e) => error!("Unable to trace to '{}': {}", filename, e),
+        }
+    }
+
+    pub fn stop_trace(&mut self) {
+        if let Some(tracer) = self.tracer.take() {
+            if let Err(e) = tracer.stop() {
+                error!("Unable to finish trace: {}", e);
+            }
+        }
+    }
+
+    pub fn trace_instruction(&mut self) {
+        if let Some(tracer) = &mut self.tracer {
+            if let Err(e) = tracer.record(&self.chip8) {
+                error!("Unable to write trace: {}", e);
+                self.tracer = None;
+            }
+        }
+    }
+
+    pub fn profile_instruction(&mut self) {
+        self.call_tree.record(&self.chip8);
+    }
+
+    pub fn play_input_recording(&mut self, filename: &str) {
+        match recording::read_input_file(filename) {
+            Ok(frames) => {
+                self.input_playback = frames;
+                self.input_playback.reverse();
+            }
+            Err(e) => error!("Unable to play input recording '{}': {}", filename, e),
+        }
+    }
+
+    // Replaces the keyboard state with the next recorded frame, returning false once playback is over
+    pub fn playing_input(&self) -> bool {
+        !self.input_playback.is_empty()
+    }
+
+    pub fn play_input_frame(&mut self) -> bool {
+        match self.input_playback.pop() {
+            Some(mask) => {
+                self.chip8.keys = recording::mask_to_keys(mask);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Puts the window back where it was when the emulator last closed
+    pub fn restore_window_position(&self) {
+        window::restore_position(self.config.get("window_position"));
+    }
+
+    // Finalises anything still being written before the window closes
+    pub fn shutdown(&mut self) {
+        self.stop_input_recording();
+        self.stop_audio_recording();
+        self.stop_trace();
+        self.save_session_history();
+        // screen_width is in framebuffer pixels, the window is created in window pixels
+        let dpi_scale = unsafe { get_internal_gl().quad_context.dpi_scale() };
+        let (width, height) = (screen_width() / dpi_scale, screen_height() / dpi_scale);
+        self.config.set("window_size", &format!("{}x{}", width.round(), height.round()));
+        // Only known on platforms where the window can be moved back there
+        if let Some((x, y)) = window::position() {
+            self.config.set("window_position", &format!("{},{}", x, y));
+        }
+    }
+
+    pub fn export_disassembly(&mut self, filename: &str) {
+        let data = self.data_ranges().clone();
+        let text = format!("; {}\n{}", self.rom_name(), opcodes::listing(&self.chip8.rom, 0x200, &data));
+        match std::fs::write(filename, text) {
+            Ok(()) => info!("Wrote disassembly to '{}'", filename),
+            Err(e) => error!("Unable to write disassembly '{}': {}", filename, e),
+        }
+    }
+
+    // Memory from 0x200 to the end of the loaded ROM, unless a range is given
+    fn modified_rom_range(&self, range: Option<Range<usize>>) -> Range<usize> {
+        range.unwrap_or(0x200..0x200 + self.chip8.rom.len())
+    }
+
+    // Writes memory back out as a ROM file, keeping any patches made since it was loaded
+    pub fn save_memory_as_rom(&self, filename: &str, range: Option<Range<usize>>) {
+        let range = self.modified_rom_range(range);
+        let Some(bytes) = self.chip8.memory().get(range.clone()) else {
+            error!("{:03X}-{:03X} isn't inside memory", range.start, range.end);
+            return;
+        };
+        match std::fs::write(filename, bytes) {
+            Ok(()) => info!("Wrote {:03X}-{:03X} to '{}'", range.start, range.end, filename),
+            Err(e) => error!("Unable to write ROM '{}': {}", filename, e),
+        }
+    }
+
+    pub fn export_instruction_history(&self, filename: &str) {
+        let text = format!(
+            "; Last {} instructions of {}, with the registers before each ran\n{}",
+            self.history_export_len,
+            self.rom_name(),
+            self.chip8.instruction_history(self.history_export_len)
+        );
+        match std::fs::write(filename, text) {
+            Ok(()) => info!("Wrote instruction history to '{}'", filename),
+            Err(e) => error!("Unable to write instruction history '{}': {}", filename, e),
+        }
+    }
+
+    pub fn save_report(&self) {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let filename = format!("chip8_report_{}.txt", secs);
+        let report = Report::capture(&self.chip8, self.speed, logbuffer::recent());
+        if let Err(e) = report.save(&filename) {
+            error!("Unable to save bug report '{}': {}", filename, e);
+        }
+    }
+
+    pub fn load_report(&mut self, filename: &str) {
+        match Report::load(filename) {
+            Ok(report) => {
+                report.restore(&mut self.chip8);
+                self.speed = report.speed;
+                self.pause_execution = true;
+            }
+            Err(e) => error!("Unable to load bug report '{}': {}", filename, e),
+        }
+    }
+
+    pub fn settings_profile(&self, name: &str) -> SettingsProfile {
+        SettingsProfile {
+            name: name.to_owned(),
+            quirks: self.chip8.quirks,
+            keypad_layout: self.keypad_layout,
+            fg_color: self.fg_color,
+            bg_color: self.bg_color,
+            speed: self.speed,
+        }
+    }
+
+    pub fn apply_settings_profile(&mut self, profile: &SettingsProfile) {
+        self.chip8.quirks = profile.quirks;
+        self.keypad_layout = profile.keypad_layout;
+        self.config.set("keypad_layout", profile.keypad_layout.name());
+        self.fg_color = profile.fg_color;
+        self.bg_color = profile.bg_color;
+        self.chip8.redraw_all();
+        self.speed = profile.speed;
+    }
+
+    // The profile is named after its file, so a shared file says what it's for
+    pub fn export_profile(&self, filename: &str) {
+        let name = std::path::Path::new(filename).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        if let Err(e) = self.settings_profile(&name).save(filename) {
+            error!("Unable to export profile '{}': {}", filename, e);
+        }
+    }
+
+    pub fn import_profile(&mut self, filename: &str) {
+        match SettingsProfile::load(filename) {
+            Ok(profile) => {
+                self.apply_settings_profile(&profile);
+                info!("Imported profile '{}'", profile.name);
+            }
+            Err(e) => error!("Unable to import profile '{}': {}", filename, e),
+        }
+    }
+
+    pub fn visuals(&self) -> egui::Visuals {
+        match self.ui_theme {
+            UiTheme::Dark => egui::Visuals::dark(),
+            UiTheme::Light => egui::Visuals::light(),
+            UiTheme::Custom => custom_visuals(&self.fg_color, &self.bg_color),
+        }
+    }
+
+    pub fn label_bold(&mut self, text: &str, ui: &mut Ui) {
+        ui.label(RichText::new(text).color(self.bold_text_color));
+    }
+
+    pub fn show_main_menubar(&mut self, egui_ctx: &Context) {
+        //pub fn show_main_menubar(&mut self, egui_ctx: &Context, chip8: &mut Chip8) {
+        egui::TopBottomPanel::top("menu_bar").show(egui_ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Load ROM").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("CHIP-8 ROM", &["ch8"])
+                            .set_directory("/")
+                            .pick_file();
+                        match files {
+                            Some(path) => {
+                                let rom = path.into_os_string().into_string().unwrap();
+                                self.load_rom(&rom);
+                            },
+                            None => ()
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Export disassembly").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("Disassembly listing", &["txt"])
+                            .save_file();
+                        if let Some(path) = files {
+                            self.export_disassembly(&path.into_os_string().into_string().unwrap());
+                        }
+                        ui.close_menu();
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Save memory as ROM").clicked() {
+                            let range = opcodes::parse_data_ranges(&self.rom_range_input).pop();
+                            if range.is_none() && !self.rom_range_input.trim().is_empty() {
+                                error!("'{}' isn't a hex address range", self.rom_range_input);
+                            } else if let Some(path) = FileDialog::new().add_filter("CHIP-8 ROM", &["ch8"]).save_file() {
+                                self.save_memory_as_rom(&path.into_os_string().into_string().unwrap(), range);
+                            }
+                            ui.close_menu();
+                        }
+                        let default = self.modified_rom_range(None);
+                        ui.add(egui::TextEdit::singleline(&mut self.rom_range_input)
+                            .desired_width(60.)
+                            .hint_text(format!("{:03X}-{:03X}", default.start, default.end)));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Export instruction history").clicked() {
+                            let files = FileDialog::new()
+                                .add_filter("Instruction history", &["txt"])
+                                .save_file();
+                            if let Some(path) = files {
+                                self.export_instruction_history(&path.into_os_string().into_string().unwrap());
+                            }
+                            ui.close_menu();
+                        }
+                        ui.add(egui::DragValue::new(&mut self.history_export_len).clamp_range(1..=PC_HISTORY_LEN).suffix(" ops"));
+                    });
+                    if ui.checkbox(&mut self.reload_on_change, "Reload ROM on change").changed() {
+                        self.update_rom_watcher();
+                    }
+                    ui.separator();
+                    if self.input_recorder.is_none() {
+                        if ui.button("Start input recording").clicked() {
+                            let files = FileDialog::new()
+                                .add_filter("CHIP-8 input recording", &["txt"])
+                                .save_file();
+                            if let Some(path) = files {
+                                self.start_input_recording(&path.into_os_string().into_string().unwrap());
+                            }
+                            ui.close_menu();
+                        }
+                    } else if ui.button("Stop input recording").clicked() {
+                        self.stop_input_recording();
+                        ui.close_menu();
+                    }
+                    if ui.button("Play input recording").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("CHIP-8 input recording", &["txt"])
+                            .pick_file();
+                        if let Some(path) = files {
+                            self.play_input_recording(&path.into_os_string().into_string().unwrap());
+                        }
+                        ui.close_menu();
+                    }
+                    if self.audio_recorder.is_none() {
+                        if ui.button("Start audio recording").clicked() {
+                            let files = FileDialog::new()
+                                .add_filter("WAV audio", &["wav"])
+                                .save_file();
+                            if let Some(path) = files {
+                                self.start_audio_recording(&path.into_os_string().into_string().unwrap());
+                            }
+                            ui.close_menu();
+                        }
+                    } else if ui.button("Stop audio recording").clicked() {
+                        self.stop_audio_recording();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if self.tracer.is_none() {
+                        if ui.button("Start trace").clicked() {
+                            let files = FileDialog::new()
+                                .add_filter("Execution trace", &["txt"])
+                                .save_file();
+                            if let Some(path) = files {
+                                self.start_trace(&path.into_os_string().into_string().unwrap());
+                            }
+                            ui.close_menu();
+                        }
+                    } else if ui.button("Stop trace").clicked() {
+                        self.stop_trace();
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.trace_draw_snapshots, "Trace registers and display on draws").changed() {
+                        if let Some(tracer) = &mut self.tracer {
+                            tracer.draw_snapshots = self.trace_draw_snapshots;
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Export settings profile").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("CHIP-8 settings profile", &["cfg"])
+                            .save_file();
+                        if let Some(path) = files {
+                            self.export_profile(&path.into_os_string().into_string().unwrap());
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Import settings profile").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("CHIP-8 settings profile", &["cfg"])
+                            .pick_file();
+                        if let Some(path) = files {
+                            self.import_profile(&path.into_os_string().into_string().unwrap());
+                        }
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Save bug report (F12)").clicked() {
+                        self.save_report();
+                        ui.close_menu();
+                    }
+                    if ui.button("Load bug report").clicked() {
+                        let files = FileDialog::new()
+                            .add_filter("CHIP-8 bug report", &["txt"])
+                            .pick_file();
+                        if let Some(path) = files {
+                            self.load_report(&path.into_os_string().into_string().unwrap());
+                        }
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.show_status_bar, "Status bar");
+                    ui.checkbox(&mut self.show_quirks, "Quirks");
+                    ui.checkbox(&mut self.show_opcode, "Opcode fields");
+                    ui.checkbox(&mut self.show_compare, "Side by side comparison");
+                    ui.checkbox(&mut self.show_memory, "Memory at I");
+                    ui.checkbox(&mut self.show_breakpoints, "Breakpoints");
+                    ui.checkbox(&mut self.show_memory_bitmap, "Memory as image");
+                    ui.checkbox(&mut self.show_opcode_status, "Implemented opcodes");
+                    ui.checkbox(&mut self.show_call_tree, "Call tree");
+                    ui.checkbox(&mut self.show_timeline, "Timeline");
+                    ui.checkbox(&mut self.show_console, "Console");
+                    ui.separator();
+                    let stretch = ui.radio_value(&mut self.display_scaling, DisplayScaling::Stretch, "Stretch to fit");
+                    let pixel_perfect = ui.radio_value(&mut self.display_scaling, DisplayScaling::PixelPerfect, "Pixel perfect");
+                    if stretch.clicked() || pixel_perfect.clicked() {
+                        self.config.set("display_scaling", self.display_scaling.name());
+                    }
+                    if ui.add(Slider::new(&mut self.display_border, 0..=MAX_DISPLAY_BORDER).text("Border")).changed() {
+                        self.config.set("display_border", &self.display_border.to_string());
+                    }
+                    let transform = self.display_transform;
+                    ui.horizontal(|ui| {
+                        ui.label("Rotate:");
+                        for rotation in Rotation::ALL {
+                            ui.radio_value(&mut self.display_transform.rotation, rotation, rotation.name());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.display_transform.flip_x, "Flip horizontally");
+                        ui.checkbox(&mut self.display_transform.flip_y, "Flip vertically");
+                    });
+                    if self.display_transform != transform {
+                        self.config.set("display_rotation", self.display_transform.rotation.name());
+                        self.config.set("display_flip_x", &self.display_transform.flip_x.to_string());
+                        self.config.set("display_flip_y", &self.display_transform.flip_y.to_string());
+                        // The textures are built already transformed
+                        self.chip8.redraw_all();
+                        if let Some(compare) = &mut self.compare {
+                            compare.redraw = true;
+                        }
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Theme:");
+                        for theme in UiTheme::ALL {
+                            if ui.radio_value(&mut self.ui_theme, theme, theme.name()).clicked() {
+                                self.config.set("ui_theme", theme.name());
+                            }
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Organize windows").clicked() {
+                        ui.ctx().memory().reset_areas();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Reset egui memory")
+                        .on_hover_text("Forget scroll, positions, sizes etc")
+                        .clicked()
+                    {
+                        *ui.ctx().memory() = Default::default();
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+    }
+
+    pub fn show_general_state(&mut self, egui_ctx: &Context) {
+        // pub fn show_general_state(&mut self, egui_ctx: &Context, chip8: &Chip8, ops_last_sec: i32, draw_last_sec: i32) {
+        egui::Window::new("General State").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            self.label_bold("CPU Info:", ui);
+            ui.horizontal_wrapped(|ui| {
+                self.label_bold("PC:", ui);
+                ui.label(format!("{:02X} ", self.chip8.pc));
+                self.label_bold("OP:", ui);
+                ui.label(format!("{:02X} ", self.chip8.opcode.code));
+                self.label_bold("IR:", ui);
+                ui.label(format!("{:02X} ", self.chip8.reg_i));
+            });
+            ui.label(format!("Seed: {:016X}", self.chip8.seed));
+            ui.label(format!("State: {:016X}", self.chip8.state_checksum()))
+                .on_hover_text("Checksum of the registers, memory and display. Two machines with the same checksum are in the same state.");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.seed_input).desired_width(110.).hint_text("Hex seed"));
+                if ui.button("Seed").clicked() {
+                    match u64::from_str_radix(self.seed_input.trim(), 16) {
+                        Ok(seed) => self.chip8.seed_rng(seed),
+                        Err(_) => error!("'{}' isn't a hex seed", self.seed_input),
+                    }
+                }
+            });
+            if ui.button("New random seed").clicked() {
+                self.chip8.seed_rng(::rand::random());
+                self.seed_input = format!("{:X}", self.chip8.seed);
+            }
+            egui::ComboBox::from_label("On reset")
+                .selected_text(self.chip8.reset_rng.name())
+                .show_ui(ui, |ui| {
+                    for reset_rng in ResetRng::ALL {
+                        if ui.selectable_value(&mut self.chip8.reset_rng, reset_rng, reset_rng.name()).clicked() {
+                            self.config.set("reset_rng", reset_rng.name());
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                let mut frozen = self.chip8.frozen_random.is_some();
+                let toggled = ui.checkbox(&mut frozen, "Freeze RNG at")
+                    .on_hover_text("CXNN uses this value instead of a random number")
+                    .changed();
+                let edited = ui.add(egui::DragValue::new(&mut self.frozen_random).clamp_range(0..=255)).changed();
+                if toggled || edited {
+                    self.chip8.frozen_random = if frozen { Some(self.frozen_random) } else { None };
+                }
+            });
+            ui.separator();
+            match self.chip8.stack.top() {
+                Some(top) => ui.label(format!("Stack: {:#04x}", top)),
+                None => ui.label("Stack: empty"),
+            };
+            // Turns amber then red as calls get close to overflowing the stack
+            let depth = self.chip8.stack.len();
+            let depth_color = match depth * 4 / stack::CAPACITY {
+                0 | 1 => self.bold_text_color,
+                2 => Color32::from_rgb(255, 200, 80),
+                _ => self.reg_write_color,
+            };
+            ui.add(
+                egui::ProgressBar::new(depth as f32 / stack::CAPACITY as f32)
+                    .text(RichText::new(format!("Depth {}/{}", depth, stack::CAPACITY)).color(depth_color)),
+            );
+            let keys_down: Vec<String> = (0..16u8)
+                .filter(|key| self.chip8.is_key_down(*key))
+                .map(|key| format!("{:X}", key))
+                .collect();
+            if keys_down.is_empty() {
+                ui.label("Keypress: none");
+            } else {
+                ui.label(format!("Keypress: {}", keys_down.join(" ")));
+            }
+            ui.label(format!("Delay timer: {}", self.chip8.delay_timer));
+            ui.label(format!("Sound timer: {}", self.chip8.sound_timer));
+            ui.label(format!("Instruction/s: {}", self.ops_last_sec));
+            ui.label(format!("Redraw/s: {}", self.draw_last_sec));
+            ui.separator();
+            ui.label(RichText::new("Registers:").color(self.bold_text_color));
+            for i in (0x0..=0xF).step_by(4) {
+                ui.horizontal_wrapped(|ui| {
+                    for j in 0..4 {
+                        let reg = i + j;
+                        let reg_color = self.reg_color(reg);
+                        ui.label(RichText::new(format!("{:0X}:", reg)).color(reg_color));
+                        ui.label(format!("{:02X} ", self.chip8.reg[reg]));
+                    }
+                });
+            }
+        });
+    }
+
+    pub fn show_controls(&mut self, egui_ctx: &Context) {
+        //pub fn show_controls(&mut self, egui_ctx: &Context, chip8: &mut Chip8, speed: &mut i32, pause_execution: &mut bool, step: &mut bool, fg_color: &mut [f32;3], bg_color: &mut [f32;3]) {
+        egui::Window::new("Control").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            ui.label(RichText::new("Execution:").color(self.bold_text_color));
+            ui.add(
+                Slider::new(&mut self.speed, 1..=1000)
+                    .logarithmic(true)
+                    .text("Speed"),
+            );
+            if ui
+                .checkbox(&mut self.lock_60hz, "Lock to 60Hz")
+                .on_hover_text("Runs exactly 60 frames a second whatever the display's refresh rate, for smooth recordings")
+                .changed()
+            {
+                self.frame_clock = FrameClock::new(get_time());
+            }
+            ui.checkbox(&mut self.catch_up, "Catch up after stalls")
+                .on_hover_text("Runs the instructions missed while the window was stalled or in the background, up to two seconds' worth, so games paced by real time stay in step");
+            ui.menu_button("Platform", |ui| {
+                for platform in &PLATFORMS {
+                    if ui.button(format!("{} ({} IPS)", platform.name, platform.ips)).clicked() {
+                        self.select_platform(platform);
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Toggle execution").clicked() {
+                    self.pause_execution = !self.pause_execution;
+                }
+                if ui.button("Step").clicked() {
+                    self.step = true;
+                }
+                if ui.button("Step to next draw").clicked() {
+                    self.step_to_draw(STEP_TO_DRAW_LIMIT);
+                }
+            });
+            if let Some((count, drew)) = self.step_to_draw_result {
+                ui.label(if drew {
+                    format!("Drew after {} instructions", count)
+                } else {
+                    format!("No draw in {} instructions", count)
+                });
+            }
+            if self.pause_execution {
+                // What Step will run, read from memory without touching the machine
+                let next = self.chip8.next_opcode();
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Next:").color(self.bold_text_color));
+                    ui.label(RichText::new(format!("{:03X}  {:04X}  {}", self.chip8.pc, next.code, opcodes::disassemble(next.code))).monospace());
+                });
+                ui.label(
+                    RichText::new(format!("X={:X} Y={:X} N={:X} NN={:02X} NNN={:03X}", next.x, next.y, next.n, next.nn, next.nnn))
+                        .monospace(),
+                )
+                .on_hover_text(opcodes::describe(next.code).1);
+            }
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.run_to_frame_input).prefix("Frame "));
+                if ui.button("Run to frame").clicked() {
+                    self.start_run_to_frame(self.run_to_frame_input);
+                }
+            });
+
+            ui.separator();
+            ui.label(RichText::new("Display Color:").color(self.bold_text_color));
+            ui.horizontal(|ui| {
+                ui.label("FG:");
+                if ui.color_edit_button_rgb(&mut self.fg_color).changed() {
+                    self.chip8.redraw_all();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("BG:");
+                if ui.color_edit_button_rgb(&mut self.bg_color).changed() {
+                    self.chip8.redraw_all();
+                }
+            });
+            let ratio = contrast_ratio(&self.fg_color, &self.bg_color);
+            if ratio < MIN_CONTRAST_RATIO {
+                ui.label(RichText::new(format!("Low contrast ({:.1}:1)", ratio)).color(self.reg_write_color));
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.beep_flash, "Flash border on beep")
+                    .on_hover_text("Colors the border around the display while the sound timer is running");
+                ui.color_edit_button_rgb(&mut self.beep_flash_color);
+            });
+
+            ui.separator();
+            ui.label(RichText::new("Keypad:").color(self.bold_text_color));
+            egui::ComboBox::from_label("Layout")
+                .selected_text(self.keypad_layout.name())
+                .show_ui(ui, |ui| {
+                    for layout in KeypadLayout::ALL {
+                        if ui.selectable_value(&mut self.keypad_layout, layout, layout.name()).clicked() {
+                            self.config.set("keypad_layout", layout.name());
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                let held = ui.radio_value(&mut self.key_input.repeat, KeyRepeat::Held, "Held")
+                    .on_hover_text("A held key stays pressed");
+                let edge = ui.radio_value(&mut self.key_input.repeat, KeyRepeat::Edge, "Press only")
+                    .on_hover_text("A key only counts for the frame it goes down");
+                if held.clicked() || edge.clicked() {
+                    self.config.set("key_repeat", self.key_input.repeat.name());
+                }
+            });
+
+            ui.separator();
+            ui.label(RichText::new("Debug:").color(self.bold_text_color));
+            ui.checkbox(&mut self.show_display_diff, "Flash display changes");
+            if ui.checkbox(&mut self.show_collisions, "Flash sprite collisions")
+                .on_hover_text("Pixels a DXYN erased, setting VF")
+                .changed()
+            {
+                self.chip8.record_collisions = self.show_collisions;
+                self.chip8.collisions.clear();
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_display_history, "Show every pixel ever set");
+                if ui.button("Clear").clicked() {
+                    self.display_history = [false; DISPLAY_SIZE];
+                }
+            });
+            if ui.checkbox(&mut self.compare_previous_session, "Compare pixels with last session")
+                .on_hover_text("Every pixel this ROM set last time it was closed or reloaded, against this time")
+                .changed()
+            {
+                self.config.set("compare_previous_session", &self.compare_previous_session.to_string());
+                self.load_previous_history();
+            }
+            ui.checkbox(&mut self.show_sprite_preview, "Preview next sprite when paused");
+            if ui.checkbox(&mut self.timers_run_while_paused, "Timers run while paused").changed() {
+                self.config.set("timers_run_while_paused", &self.timers_run_while_paused.to_string());
+            }
+            egui::ComboBox::from_label("PC out of range")
+                .selected_text(self.chip8.pc_policy.name())
+                .show_ui(ui, |ui| {
+                    for policy in PcPolicy::ALL {
+                        ui.selectable_value(&mut self.chip8.pc_policy, policy, policy.name());
+                    }
+                });
+            ui.checkbox(&mut self.chip8.warn_pc_range, "Warn when PC leaves 0x200-0xFFF");
+            ui.checkbox(&mut self.chip8.protect_low_memory, "Protect memory below 0x200")
+                .on_hover_text("Halts on any write to the font and interpreter area. Leave off for ROMs that rewrite the font on purpose.");
+            ui.checkbox(&mut self.chip8.warn_overflow, "Warn when arithmetic wraps")
+                .on_hover_text("Logs the operands whenever 7XNN, 8XY4, 8XY5 or 8XY7 carries past 255 or borrows below 0");
+            ui.checkbox(&mut self.persist_reg_highlights, "Keep register highlights");
+            if self.persist_reg_highlights {
+                ui.add(
+                    Slider::new(&mut self.reg_highlight_frames, 1..=120)
+                        .text("Highlight frames"),
+                );
+            }
+            ui.checkbox(&mut self.show_audio_visualizer, "Audio visualizer");
+            ui.checkbox(&mut self.chip8.detect_idle_loop, "Stop at idle loops");
+            ui.checkbox(&mut self.chip8.log_unknown_once, "Log unknown opcodes once");
+            ui.add(
+                Slider::new(&mut self.chip8.sound_timer_slowdown, 1..=8)
+                    .suffix("x")
+                    .text("Sound timer slowdown"),
+            );
+        });
+    }
+
+    pub fn show_disassembly(&mut self, egui_ctx: &Context) {
+        egui::Window::new("Disassembly").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            let data = self.data_ranges().clone();
+            for line in 0..DISASSEMBLY_LINES {
+                let address = (self.chip8.pc + line * 2) & 0xFFF;
+                let code = self.chip8.opcode_at(address);
+                let (pattern, description) = opcodes::describe(code);
+                let text = if opcodes::is_data(&data, address) {
+                    format!("{:03X}: {:04X} DB {:#04X}, {:#04X}", address, code, code >> 8, code & 0xFF)
+                } else {
+                    format!("{:03X}: {:04X} {}", address, code, opcodes::disassemble(code))
+                };
+                let text = if line == 0 { RichText::new(text).color(self.bold_text_color) } else { RichText::new(text) };
+                ui.label(text).on_hover_text(format!("{} - {}", pattern, description));
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.data_range_input).desired_width(60.).hint_text("200-20F"));
+                let range = opcodes::parse_data_ranges(&self.data_range_input).pop();
+                let data = ui.button("Data").on_hover_text("Show these addresses as bytes, not instructions");
+                let code = ui.button("Code");
+                match range {
+                    Some(range) if data.clicked() || code.clicked() => self.mark_data(range, data.clicked()),
+                    None if data.clicked() || code.clicked() => error!("'{}' isn't a hex address range", self.data_range_input),
+                    _ => (),
+                }
+            });
+            if !data.is_empty() {
+                ui.label(format!("Data: {}", opcodes::format_data_ranges(&data)));
+            }
+        });
+    }
+
+    // A hex view of the memory around I, with the byte it points at highlighted
+    pub fn show_memory(&mut self, egui_ctx: &Context) {
+        if !self.show_memory {
+            return;
+        }
+        egui::Window::new("Memory").show(egui_ctx, |ui| {
+            let address = self.chip8.reg_i as usize;
+            match self.chip8.memory().get(address) {
+                Some(byte) => ui.label(format!("I = {:03X} -> {:02X} ({:08b})", address, byte, byte)),
+                // Only possible with the 16-bit I register quirk
+                None => ui.label(RichText::new(format!("I = {:04X} is outside memory", address)).color(self.reg_write_color)),
+            };
+            ui.separator();
+            let first_row = memory_window_start(address, self.chip8.memory().len(), MEMORY_ROWS);
+            for row in (first_row..first_row + MEMORY_ROWS * 16).step_by(16) {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.;
+                    let marker = if (row..row + 16).contains(&address) { ">" } else { " " };
+                    ui.label(RichText::new(format!("{}{:03X}:", marker, row)).monospace().color(self.bold_text_color));
+                    for (offset, byte) in self.chip8.memory()[row..row + 16].iter().enumerate() {
+                        let text = RichText::new(format!("{:02X}", byte)).monospace();
+                        ui.label(if row + offset == address { text.color(self.reg_write_color).underline() } else { text });
+                    }
+                });
+            }
+        });
+    }
+
+    // Every opcode in green if the interpreter implements it, red if it's still a stub
+    pub fn show_opcode_status(&mut self, egui_ctx: &Context) {
+        if !self.show_opcode_status {
+            return;
+        }
+        let (implemented_color, missing_color) = (self.bold_text_color, self.reg_write_color);
+        egui::Window::new("Implemented opcodes").show(egui_ctx, |ui| {
+            let implemented = opcodes::OPCODES.iter().filter(|info| info.implemented()).count();
+            ui.label(format!("{} of {} implemented", implemented, opcodes::OPCODES.len()));
+            ui.separator();
+            egui::Grid::new("opcode_status").show(ui, |ui| {
+                for (i, info) in opcodes::OPCODES.iter().enumerate() {
+                    let color = if info.implemented() { implemented_color } else { missing_color };
+                    ui.label(RichText::new(info.pattern).monospace().color(color)).on_hover_text(info.description);
+                    if i % 5 == 4 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+    }
+
+    // A strip per frame showing draws, sound and keys held. Clicking a frame jumps back to it.
+    pub fn show_timeline(&mut self, egui_ctx: &Context) {
+        if !self.show_timeline {
+            return;
+        }
+        const COLUMN_WIDTH: f32 = 3.;
+        const ROW_HEIGHT: f32 = 10.;
+        let rows = [
+            ("Draw", Color32::from_rgb(110, 255, 110)),
+            ("Sound", Color32::from_rgb(255, 160, 60)),
+            ("Keys", Color32::from_rgb(100, 160, 255)),
+        ];
+        egui::Window::new("Timeline").show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (name, color) in rows {
+                    ui.label(RichText::new(format!("■ {}", name)).color(color));
+                }
+            });
+            let mut jump = None;
+            egui::ScrollArea::horizontal().stick_to_right().show(ui, |ui| {
+                let frames = &self.timeline.frames;
+                let size = egui::vec2(frames.len() as f32 * COLUMN_WIDTH, rows.len() as f32 * ROW_HEIGHT);
+                let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+                let origin = response.rect.min;
+                for (i, entry) in frames.iter().enumerate() {
+                    let active = [entry.drew, entry.sound, entry.keys != 0];
+                    for (row, (_, color)) in rows.iter().enumerate() {
+                        if active[row] {
+                            let min = origin + egui::vec2(i as f32 * COLUMN_WIDTH, row as f32 * ROW_HEIGHT);
+                            painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(COLUMN_WIDTH, ROW_HEIGHT - 1.)), 0., *color);
+                        }
+                    }
+                }
+                let hovered = response.hover_pos().map(|pos| ((pos.x - origin.x) / COLUMN_WIDTH) as usize);
+                if let Some(entry) = hovered.and_then(|i| frames.get(i)) {
+                    let keys: Vec<String> = (0..16).filter(|key| entry.keys & 1 << key != 0).map(|key| format!("{:X}", key)).collect();
+                    response.clone().on_hover_text(format!("Frame {}, keys {}", entry.frame, if keys.is_empty() { "none".to_owned() } else { keys.join(" ") }));
+                }
+                if response.clicked() {
+                    jump = hovered;
+                }
+            });
+            if let Some(frame) = jump.and_then(|i| self.timeline.jump_to(i, &mut self.chip8)) {
+                self.frame = frame + 1;
+                self.pause_execution = true;
+            }
+            ui.label(format!("{} frames", self.timeline.frames.len()));
+        });
+    }
+
+    // Where the instructions run since the ROM was loaded went, per subroutine
+    pub fn show_call_tree(&mut self, egui_ctx: &Context) {
+        if !self.show_call_tree {
+            return;
+        }
+        egui::Window::new("Call tree").show(egui_ctx, |ui| {
+            if ui.button("Clear").clicked() {
+                self.call_tree.clear();
+            }
+            ui.separator();
+            let total = self.call_tree.root.total();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                call_node_ui(ui, &self.call_tree.root, total, "call_tree");
+            });
+        });
+    }
+
+    // Memory drawn as a 1-bit bitmap like DXYN would, so sprite data stands out from code
+    pub fn show_memory_bitmap(&mut self, egui_ctx: &Context) {
+        if !self.show_memory_bitmap {
+            return;
+        }
+        egui::Window::new("Memory as image").show(egui_ctx, |ui| {
+            let memory_len = self.chip8.memory().len();
+            ui.horizontal(|ui| {
+                ui.label("Start");
+                ui.add(egui::DragValue::new(&mut self.bitmap_start).clamp_range(0..=memory_len - 1));
+                ui.label(format!("{:03X}", self.bitmap_start));
+                ui.label("Width");
+                ui.add(egui::DragValue::new(&mut self.bitmap_width).clamp_range(1..=8).suffix(" bytes"));
+            });
+            let pixels = memory_bitmap(self.chip8.memory(), self.bitmap_start, self.bitmap_width, BITMAP_ROWS);
+            let width = self.bitmap_width * 8;
+            let size = egui::vec2(width as f32, BITMAP_ROWS as f32) * BITMAP_SCALE;
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0., Color32::BLACK);
+            for (i, _) in pixels.iter().enumerate().filter(|(_, set)| **set) {
+                let min = rect.min + egui::vec2((i % width) as f32, (i / width) as f32) * BITMAP_SCALE;
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(BITMAP_SCALE, BITMAP_SCALE)), 0., Color32::WHITE);
+            }
+            let end = (self.bitmap_start + self.bitmap_width * BITMAP_ROWS).min(memory_len);
+            ui.label(format!("{:03X}-{:03X}", self.bitmap_start, end - 1));
+        });
+    }
+
+    // A command line for peeking and poking the machine, with its scrollback
+    pub fn show_console(&mut self, egui_ctx: &Context) {
+        if !self.show_console {
+            return;
+        }
+        egui::Window::new("Console").show(egui_ctx, |ui| {
+            egui::ScrollArea::vertical().max_height(200.).stick_to_bottom().show(ui, |ui| {
+                for line in &self.console.scrollback {
+                    ui.label(RichText::new(line).monospace());
+                }
+            });
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.console.input)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text(console::HELP),
+            );
+            if input.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                self.console.submit(&mut self.chip8, &mut self.pc_hooks);
+                input.request_focus();
+            }
+        });
+    }
+
+    // Actions to run when the PC reaches an address, from pausing to poking a register
+    pub fn show_breakpoints(&mut self, egui_ctx: &Context) {
+        if !self.show_breakpoints {
+            return;
+        }
+        egui::Window::new("Breakpoints").show(egui_ctx, |ui| {
+            let mut remove = None;
+            for (i, hook) in self.pc_hooks.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("{:03X}", hook.pc)).monospace().color(self.bold_text_color));
+                    ui.label(hook.action.describe());
+                    if !hook.armed && ui.small_button("Re-arm").clicked() {
+                        hook.armed = true;
+                    }
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                self.pc_hooks.remove(i);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("PC");
+                ui.add(egui::TextEdit::singleline(&mut self.hook_pc_input).desired_width(40.));
+                egui::ComboBox::from_id_source("hook_action")
+                    .selected_text(self.hook_action.name())
+                    .show_ui(ui, |ui| {
+                        for action in PcAction::ALL {
+                            if ui.selectable_label(self.hook_action.name() == action.name(), action.name()).clicked() {
+                                self.hook_action = action;
+                            }
+                        }
+                    });
+            });
+            if let PcAction::SetRegister { reg, value } = &mut self.hook_action {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(reg).clamp_range(0..=15).prefix("V"));
+                    ui.add(egui::DragValue::new(value).clamp_range(0..=255).prefix("= "));
+                });
+            }
+            if let PcAction::SaveState { slot } = &mut self.hook_action {
+                ui.horizontal(|ui| {
+                    ui.label("Slot");
+                    for i in 0..SAVE_STATE_SLOTS {
+                        ui.radio_value(slot, i, format!("{}", i + 1));
+                    }
+                });
+            }
+            if ui.button("Add").clicked() {
+                match usize::from_str_radix(self.hook_pc_input.trim().trim_start_matches("0x"), 16) {
+                    Ok(pc) if pc < self.chip8.memory().len() => {
+                        self.pc_hooks.push(PcHook { pc, action: self.hook_action, armed: true });
+                        self.hook_pc_input.clear();
+                    }
+                    _ => error!("Bad breakpoint address '{}'", self.hook_pc_input),
+                }
+            }
+
+            ui.separator();
+            self.label_bold("Save states:", ui);
+            ui.horizontal(|ui| {
+                for slot in 0..SAVE_STATE_SLOTS {
+                    if ui.small_button(format!("Save {}", slot + 1)).clicked() {
+                        self.save_states[slot] = Some(self.chip8.snapshot());
+                    }
+                    let load = ui.add_enabled(self.save_states[slot].is_some(), egui::Button::new(format!("Load {}", slot + 1)).small());
+                    if load.clicked() {
+                        if let Some(snapshot) = &self.save_states[slot] {
+                            self.chip8.restore(snapshot);
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            self.label_bold("Break before any:", ui);
+            let mut remove = None;
+            ui.horizontal_wrapped(|ui| {
+                for (i, opcode_break) in self.opcode_breaks.iter().enumerate() {
+                    if ui.small_button(format!("{} x", opcode_break.name())).clicked() {
+                        remove = Some(i);
+                    }
+                }
+            });
+            if let Some(i) = remove {
+                self.opcode_breaks.remove(i);
+            }
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("opcode_break")
+                    .selected_text(self.opcode_break_choice.name())
+                    .show_ui(ui, |ui| {
+                        let choices = opcodes::OPCODES.iter().map(|info| OpcodeBreak::Pattern(info.pattern));
+                        for choice in std::iter::once(OpcodeBreak::Unimplemented).chain(choices) {
+                            ui.selectable_value(&mut self.opcode_break_choice, choice, choice.name());
+                        }
+                    });
+                if ui.button("Arm").clicked() && !self.opcode_breaks.contains(&self.opcode_break_choice) {
+                    self.opcode_breaks.push(self.opcode_break_choice);
+                }
+            });
+        });
+    }
+
+    pub fn show_compare(&mut self, egui_ctx: &Context) {
+        if !self.show_compare {
+            return;
+        }
+        egui::Window::new("Comparison").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            ui.label("Runs a second machine beside the main one, with the same keys held.");
+            ui.horizontal(|ui| {
+                if ui.button("Same ROM").clicked() {
+                    self.start_compare(None);
+                }
+                if ui.button("Other ROM").clicked() {
+                    let files = FileDialog::new()
+                        .add_filter("CHIP-8 ROM", &["ch8"])
+                        .pick_file();
+                    if let Some(path) = files {
+                        self.start_compare(Some(&path.into_os_string().into_string().unwrap()));
+                    }
+                }
+                if ui.add_enabled(self.compare.is_some(), egui::Button::new("Stop")).clicked() {
+                    self.compare = None;
+                }
+            });
+            let compare = match &mut self.compare {
+                Some(compare) => compare,
+                None => return,
+            };
+            ui.separator();
+            ui.label(RichText::new("Right side:").color(self.bold_text_color));
+            egui::ComboBox::from_label("Quirks")
+                .selected_text(compare.quirks.profile_name())
+                .show_ui(ui, |ui| {
+                    for profile in Profile::ALL {
+                        if ui.selectable_label(compare.quirks.profile() == Some(profile), profile.name()).clicked() {
+                            compare.quirks = Quirks::from_profile(profile);
+                        }
+                    }
+                });
+            ui.label(format!("PC: {:03X}  I: {:03X}", compare.pc, compare.reg_i));
+            let differs = compare.reg != self.chip8.reg;
+            ui.label(if differs { "Registers differ from the left" } else { "Registers match the left" });
+            let checksum = compare.state_checksum();
+            let matches = checksum == self.chip8.state_checksum();
+            ui.label(format!("State: {:016X} ({})", checksum, if matches { "matches" } else { "differs" }));
+        });
+    }
+
+    // Breaks the last executed opcode down into the fields the handlers use
+    pub fn show_opcode(&mut self, egui_ctx: &Context) {
+        if !self.show_opcode {
+            return;
+        }
+        egui::Window::new("Opcode").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            let opcode = &self.chip8.opcode;
+            let (pattern, description) = opcodes::describe(opcode.code);
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Bytes:").color(self.bold_text_color));
+                ui.label(format!("{:02X} {:02X}", opcode.code >> 8, opcode.code & 0xFF));
+                ui.label(RichText::new("Pattern:").color(self.bold_text_color));
+                ui.label(pattern);
+            });
+            ui.label(RichText::new(opcodes::disassemble(opcode.code)).monospace());
+            ui.separator();
+            egui::Grid::new("opcode_fields").show(ui, |ui| {
+                for (name, value) in [
+                    ("X", format!("{:X}", opcode.x)),
+                    ("Y", format!("{:X}", opcode.y)),
+                    ("N", format!("{:X}", opcode.n)),
+                    ("NN", format!("{:02X}", opcode.nn)),
+                    ("NNN", format!("{:03X}", opcode.nnn)),
+                ] {
+                    ui.label(RichText::new(name).color(self.bold_text_color));
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.label(description);
+        });
+    }
+
+    pub fn show_quirks(&mut self, egui_ctx: &Context) {
+        if !self.show_quirks {
+            return;
+        }
+        egui::Window::new("Quirks").show(egui_ctx, |ui| {
+            ui.set_max_width(190.);
+            egui::ComboBox::from_label("Profile")
+                .selected_text(self.chip8.quirks.profile_name())
+                .show_ui(ui, |ui| {
+                    for profile in Profile::ALL {
+                        if ui.selectable_label(self.chip8.quirks.profile() == Some(profile), profile.name()).clicked() {
+                            self.chip8.quirks = Quirks::from_profile(profile);
+                        }
+                    }
+                });
+
+            ui.separator();
+            self.label_bold("Sprite edges:", ui);
+            // The screen with a wrap checkbox on each of its sides
+            let quirks = &mut self.chip8.quirks;
+            egui::Grid::new("quirk_edges").show(ui, |ui| {
+                ui.label("");
+                ui.checkbox(&mut quirks.wrap_top, "Top");
+                ui.end_row();
+                ui.checkbox(&mut quirks.wrap_left, "Left");
+                ui.label(RichText::new("Screen").monospace());
+                ui.checkbox(&mut quirks.wrap_right, "Right");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(&mut quirks.wrap_bottom, "Bottom");
+                ui.end_row();
+            });
+            ui.label("Ticked edges wrap, the rest clip. A pixel only wraps when the edge it leaves through and the opposite edge both wrap.");
+
+            ui.separator();
+            ui.checkbox(&mut self.chip8.quirks.wide_index, "16-bit I register");
+            ui.checkbox(&mut self.chip8.quirks.vf_reset, "Logic ops reset VF");
+            ui.checkbox(&mut self.chip8.quirks.index_overflow_vf, "FX1E sets VF past 0xFFF")
+                .on_hover_text("The Amiga interpreter set VF when I + VX went past 0xFFF. A few games rely on it.");
+            ui.checkbox(&mut self.chip8.quirks.jump_vx, "BNNN jumps by VX")
+                .on_hover_text("CHIP-48 and SUPER-CHIP add VX, where X is the top digit of NNN, instead of V0.");
+            ui.checkbox(&mut self.chip8.quirks.shift_vx, "Shifts ignore VY")
+                .on_hover_text("CHIP-48 and SUPER-CHIP shift VX in place. The COSMAC VIP set VX to VY shifted.");
+            ui.checkbox(&mut self.chip8.quirks.half_scroll_lowres, "Half-pixel low-res scroll")
+                .on_hover_text("SUPER-CHIP 1.1 scrolled half as far in low-res. Octo and most modern interpreters don't.");
+            ui.add(egui::DragValue::new(&mut self.chip8.quirks.horizontal_scroll)
+                .clamp_range(1..=quirks::MAX_HORIZONTAL_SCROLL)
+                .prefix("00FB/00FC scroll ")
+                .suffix(" px"));
+            ui.checkbox(&mut self.chip8.quirks.vf_clipped_rows, "High-res VF counts rows")
+                .on_hover_text("SUPER-CHIP 1.1 set VF to the number of sprite rows that collided or fell off the bottom in high-res.");
+            ui.checkbox(&mut self.chip8.quirks.sound_floor, "Sound timer needs 2+")
+                .on_hover_text("The COSMAC VIP made no sound for a sound timer of 1, so FX18 treats values below 2 as 0.");
+            if ui.checkbox(&mut self.chip8.quirks.fx0a_polling, "FX0A doesn't wait (this ROM)")
+                .on_hover_text("Non-standard. FX0A stores the last key pressed and carries on instead of waiting for a key, as some buggy interpreters did. Saved for the current ROM.")
+                .changed()
+            {
+                let key = format!("fx0a_polling_{:016x}", self.chip8.rom_hash());
+                self.config.set(&key, &self.chip8.quirks.fx0a_polling.to_string());
+            }
+            ui.checkbox(&mut self.chip8.quirks.single_key, "One key at a time")
+                .on_hover_text("EX9E and EXA1 only see the lowest key held, like interpreters that read a single key code.");
+            egui::ComboBox::from_label("FX55/FX65")
+                .selected_text(self.chip8.quirks.load_store.name())
+                .show_ui(ui, |ui| {
+                    for load_store in LoadStore::ALL {
+                        ui.selectable_value(&mut self.chip8.quirks.load_store, load_store, load_store.name());
+                    }
+                });
+        });
+    }
+
+    // Plots this frame's beeper output like an oscilloscope
+    pub fn show_audio_visualizer(&mut self, egui_ctx: &Context) {
+        if !self.show_audio_visualizer {
+            return;
+        }
+        egui::Window::new("Audio").show(egui_ctx, |ui| {
+            let (response, painter) = ui.allocate_painter(egui::vec2(190., 60.), egui::Sense::hover());
+            let rect = response.rect;
+            let samples = &self.beeper.samples;
+            let points = samples
+                .iter()
+                .enumerate()
+                .map(|(i, sample)| {
+                    let x = rect.left() + rect.width() * i as f32 / samples.len() as f32;
+                    egui::pos2(x, rect.center().y - sample * rect.height())
+                })
+                .collect();
+            painter.rect_filled(rect, 0., Color32::BLACK);
+            painter.add(egui::Shape::line(points, (1., self.bold_text_color)));
+        });
+    }
+
+    pub fn show_status_bar(&mut self, egui_ctx: &Context) {
+        if !self.show_status_bar {
+            return;
+        }
+        egui::TopBottomPanel::bottom("status_bar").show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                if self.pause_execution {
+                    ui.label(RichText::new("Paused").color(self.reg_write_color));
+                } else if self.chip8.halted {
+                    ui.label(RichText::new("Halted, PC left the program area").color(self.reg_write_color));
+                } else if self.chip8.spinning {
+                    ui.label(RichText::new("Program finished (idle loop)").color(self.reg_read_color));
+                } else {
+                    ui.label(RichText::new("Running").color(self.bold_text_color));
+                }
+                ui.separator();
+                ui.label(self.rom_name());
+                ui.separator();
+                ui.label(self.chip8.quirks.profile_name());
+                ui.separator();
+                ui.label(format!("{} IPS", self.ops_last_sec));
+                ui.separator();
+                match self.run_to_frame {
+                    Some(target) => ui.label(format!("Frame {} / {}", self.frame, target)),
+                    None => ui.label(format!("Frame {}", self.frame)),
+                };
+                if let Some((x, y)) = self.hovered_pixel {
+                    let state = if self.chip8.frame().pixel(x, y) { "on" } else { "off" };
+                    ui.separator();
+                    ui.label(format!("Pixel ({}, {}) {}", x, y, state));
+                }
+                if self.input_recorder.is_some() {
+                    ui.separator();
+                    ui.label(RichText::new("Recording input").color(self.reg_write_color));
+                }
+            });
+        });
+    }
+}
+
+// One routine and, collapsed under it, the routines it called
+fn call_node_ui(ui: &mut Ui, node: &CallNode, total: u64, id: &str) {
+    let node_total = node.total();
+    let percent = if total == 0 { 0. } else { node_total as f64 * 100. / total as f64 };
+    let label = format!("{:03X}: {} ({:.1}%), {} in itself", node.entry, node_total, percent, node.instructions);
+    if node.children.is_empty() {
+        ui.label(RichText::new(label).monospace());
+        return;
+    }
+    let id = format!("{}_{:03x}", id, node.entry);
+    egui::CollapsingHeader::new(RichText::new(label).monospace()).id_source(&id).default_open(true).show(ui, |ui| {
+        let mut children: Vec<&CallNode> = node.children.iter().collect();
+        children.sort_by_key(|child| std::cmp::Reverse(child.total()));
+        for child in children {
+            call_node_ui(ui, child, total, &id);
+        }
+    });
+}
+
+// Pixels for rows of width_bytes bytes from start, left to right then top to bottom.
+// Rows that run off the end of memory are blank.
+fn memory_bitmap(memory: &[u8], start: usize, width_bytes: usize, rows: usize) -> Vec<bool> {
+    (start..start + width_bytes * rows)
+        .flat_map(|address| byte_pixels(memory.get(address).copied().unwrap_or(0)))
+        .collect()
+}
+
+// The first address of a hex view `rows` lines of 16 bytes long that keeps `address` near the middle,
+// stopping at either end of memory
+fn memory_window_start(address: usize, memory_len: usize, rows: usize) -> usize {
+    let row = address.min(memory_len - 1) & !0xF;
+    row.saturating_sub(rows / 2 * 16).min(memory_len - rows * 16)
+}
+
+// Packs a pixel history into hex digits, four pixels each, to keep in the config
+fn history_to_hex(history: &[bool; DISPLAY_SIZE]) -> String {
+    history
+        .chunks(4)
+        .map(|pixels| {
+            let nibble = pixels.iter().fold(0, |nibble, set| nibble << 1 | *set as u32);
+            std::char::from_digit(nibble, 16).unwrap()
+        })
+        .collect()
+}
+
+fn history_from_hex(text: &str) -> Option<[bool; DISPLAY_SIZE]> {
+    if text.len() != DISPLAY_SIZE / 4 {
+        return None;
+    }
+    let mut history = [false; DISPLAY_SIZE];
+    for (pixels, digit) in history.chunks_mut(4).zip(text.chars()) {
+        let nibble = digit.to_digit(16)?;
+        for (bit, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = nibble & 8 >> bit != 0;
+        }
+    }
+    Some(history)
+}
+
+// Blends from one color towards another, t going from 0 to 1
+fn fade_color(from: Color32, to: Color32, t: f32) -> Color32 {
+    let t = t.clamp(0., 1.);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgb(mix(from.r(), to.r()), mix(from.g(), to.g()), mix(from.b(), to.b()))
+}
+
+// Windows filled with the display's background colour and written in its foreground colour
+fn custom_visuals(fg_color: &[f32; 3], bg_color: &[f32; 3]) -> egui::Visuals {
+    let to_color32 = |color: &[f32; 3]| {
+        let [r, g, b] = color.map(|c| (c.clamp(0., 1.) * 255.) as u8);
+        Color32::from_rgb(r, g, b)
+    };
+    let (fg, bg) = (to_color32(fg_color), to_color32(bg_color));
+    let mut visuals = if luminance(bg_color) > 0.5 { egui::Visuals::light() } else { egui::Visuals::dark() };
+    visuals.widgets.noninteractive.bg_fill = bg;
+    visuals.override_text_color = Some(fg);
+    visuals.hyperlink_color = fg;
+    visuals.selection.bg_fill = fg.linear_multiply(0.4);
+    visuals
+}
+
+fn luminance(color: &[f32; 3]) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+fn contrast_ratio(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_platform_sets_quirks_and_speed() {
+        let mut app = App::new();
+        let xo_chip = PLATFORMS.iter().find(|platform| platform.profile == Profile::XoChip).unwrap();
+        app.select_platform(xo_chip);
+        assert_eq!(app.chip8.quirks, Quirks::from_profile(Profile::XoChip));
+        assert_eq!(app.target_ips(), 60000);
+        app.select_platform(&PLATFORMS[0]);
+        assert_eq!(app.chip8.quirks.profile(), Some(Profile::CosmacVip));
+        assert_eq!(app.target_ips(), 540);
+    }
+
+    #[test]
+    fn test_compare_runs_alongside() {
+        let mut app = App::new();
+        app.chip8.load_vec(vec![0x7001, 0x1200]).unwrap();
+        app.start_compare(None);
+        app.compare.as_mut().unwrap().quirks.vf_reset = false;
+        app.run_instructions(4, None);
+        app.tick_timers();
+        let compare = app.compare.as_ref().unwrap();
+        assert_eq!(compare.reg[0], app.chip8.reg[0]);
+        assert_eq!(compare.rom, app.chip8.rom);
+        assert_ne!(compare.quirks, app.chip8.quirks);
+    }
+
+    #[test]
+    fn test_border_flashes_while_beeping() {
+        let mut app = App::new();
+        app.chip8.sound_timer = 5;
+        assert_eq!(app.border_color(&app.chip8), GRAY);
+        app.beep_flash = true;
+        assert_eq!(app.border_color(&app.chip8), Color::new(1., 0.6, 0., 1.));
+        app.chip8.sound_timer = 0;
+        assert_eq!(app.border_color(&app.chip8), GRAY);
+    }
+
+    #[test]
+    fn test_load_hex() {
+        let mut app = App::new();
+        app.load_hex("00E0 A22A 600C").unwrap();
+        assert_eq!(app.chip8.rom, vec![0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C]);
+        assert_eq!(app.chip8.memory[0x200..0x206], app.chip8.rom[..]);
+        assert!(app.load_hex("00E0 nope").is_err());
+        assert_eq!(app.chip8.rom.len(), 6); // A bad program leaves the last one loaded
+        assert!(app.load_hex(&"1200 ".repeat(0x701)).is_err());
+        assert_eq!(app.chip8.rom.len(), 6);
+    }
+
+    #[test]
+    fn test_display_transform() {
+        let dimensions = (64, 32);
+        let none = DisplayTransform { rotation: Rotation::None, flip_x: false, flip_y: false };
+        let mut transform = none;
+        assert_eq!(transform.apply((0, 0), dimensions), (0, 0));
+        transform.rotation = Rotation::Quarter;
+        // Turning clockwise takes the top left corner to the top right of a 32x64 display
+        assert_eq!(transform.size(dimensions), (32, 64));
+        assert_eq!(transform.apply((0, 0), dimensions), (31, 0));
+        assert_eq!(transform.apply((63, 0), dimensions), (31, 63));
+        transform.rotation = Rotation::ThreeQuarters;
+        assert_eq!(transform.apply((0, 0), dimensions), (0, 63));
+        transform = DisplayTransform { rotation: Rotation::Half, flip_x: true, flip_y: false };
+        assert_eq!(transform.apply((0, 0), dimensions), (0, 31));
+        for rotation in Rotation::ALL {
+            let transform = DisplayTransform { rotation, flip_x: true, flip_y: true };
+            for pixel in [(0, 0), (5, 9), (63, 31)] {
+                assert_eq!(transform.invert(transform.apply(pixel, dimensions), dimensions), pixel);
+            }
+        }
+        // A portrait display is centred in the landscape display area
+        assert_eq!(DisplayTransform { rotation: Rotation::Quarter, ..none }.layout(dimensions), (24., 0., 0.5));
+        assert_eq!(none.layout((128, 64)), (0., 0., 0.5));
+
+        let rect = DirtyRect { x: 0, y: 0, width: 8, height: 4 };
+        assert_eq!(DisplayTransform { rotation: Rotation::Quarter, ..none }.apply_rect(rect, dimensions), DirtyRect { x: 28, y: 0, width: 4, height: 8 });
+        assert_eq!(DisplayTransform { flip_x: true, ..none }.apply_rect(rect, dimensions), DirtyRect { x: 56, y: 0, width: 8, height: 4 });
+    }
+
+    #[test]
+    fn test_session_history_round_trip() {
+        let filename = std::env::temp_dir().join("chip8_test_session.cfg");
+        let filename = filename.to_str().unwrap();
+        let _ = std::fs::remove_file(filename);
+        let mut app = App::new();
+        app.config = Config::load(filename);
+        app.compare_previous_session = true;
+        app.rom_path = Some("game.ch8".to_owned());
+        for i in [0, 5, 64, DISPLAY_SIZE - 1] {
+            app.display_history[i] = true;
+        }
+        app.save_session_history();
+
+        let mut next = App::new();
+        next.config = Config::load(filename);
+        next.compare_previous_session = true;
+        next.rom_path = Some("game.ch8".to_owned());
+        next.load_previous_history();
+        assert_eq!(next.previous_history, Some(app.display_history));
+        next.rom_path = Some("other.ch8".to_owned());
+        next.load_previous_history();
+        assert_eq!(next.previous_history, None);
+        assert_eq!(history_from_hex("0f"), None);
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_save_memory_as_rom() {
+        let filename = std::env::temp_dir().join("chip8_test_patched.ch8");
+        let filename = filename.to_str().unwrap();
+        let mut app = App::new();
+        app.load_hex("00E0 A22A 600C").unwrap();
+        app.chip8.patch(0x205, &[0x0D]).unwrap();
+        app.save_memory_as_rom(filename, None);
+        assert_eq!(std::fs::read(filename).unwrap(), vec![0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0D]);
+        app.save_memory_as_rom(filename, Some(0x204..0x208));
+        assert_eq!(std::fs::read(filename).unwrap(), vec![0x60, 0x0D, 0x00, 0x00]);
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_step_to_draw() {
+        let mut app = App::new();
+        app.load_hex("6005 7001 7001 D015 1208").unwrap();
+        assert_eq!(app.step_to_draw(100), (4, true));
+        assert!(app.pause_execution);
+        assert_eq!(app.chip8.reg[0], 7);
+        assert!(app.chip8.redraw);
+        app.chip8.redraw = false;
+        // Only the idle loop is left, so it stops there without drawing
+        assert_eq!(app.step_to_draw(100), (1, false));
+
+        app.load_hex("7001 1200").unwrap();
+        assert_eq!(app.step_to_draw(50), (50, false));
+
+        // A pause hook before the draw stops it there
+        app.load_hex("6005 7001 D015").unwrap();
+        app.pc_hooks.push(PcHook { pc: 0x204, action: PcAction::Pause, armed: true });
+        assert_eq!(app.step_to_draw(100), (2, false));
+        assert_eq!(app.chip8.pc, 0x204);
+        assert!(app.pause_execution);
+        assert_eq!(app.step_to_draw(100), (1, true));
+    }
+
+    #[test]
+    fn test_display_history() {
+        let mut app = App::new();
+        app.chip8.display[3] = true;
+        app.update_display_history();
+        app.chip8.display[3] = false;
+        app.chip8.display[5] = true;
+        app.update_display_history();
+        assert!(app.display_history[3] && app.display_history[5]);
+        assert!(!app.display_history[4]);
+        app.load_hex("1200").unwrap();
+        assert!(!app.display_history.contains(&true));
+    }
+
+    #[test]
+    fn test_pc_hook_sets_register() {
+        let mut app = App::new();
+        app.load_hex("6001 6002 6003 1206").unwrap();
+        app.pc_hooks.push(PcHook { pc: 0x204, action: PcAction::SetRegister { reg: 5, value: 3 }, armed: true });
+        app.run_instructions(1, None);
+        assert_eq!(app.chip8.reg[5], 0);
+        app.run_instructions(1, None);
+        assert_eq!(app.chip8.pc, 0x204);
+        assert_eq!(app.chip8.reg[5], 3);
+
+        app.pc_hooks.push(PcHook { pc: 0x206, action: PcAction::Pause, armed: true });
+        app.pause_execution = false;
+        app.run_instructions(10, None);
+        assert!(app.pause_execution);
+        assert_eq!(app.chip8.pc, 0x206);
+    }
+
+    #[test]
+    fn test_pc_hook_saves_state_once() {
+        let mut app = App::new();
+        app.load_hex("7001 1200").unwrap();
+        app.pc_hooks.push(PcHook { pc: 0x202, action: PcAction::SaveState { slot: 2 }, armed: true });
+        app.run_instructions(1, None);
+        let saved = app.save_states[2].clone().unwrap();
+        assert_eq!(saved.reg[0], 1);
+        assert!(!app.pc_hooks[0].armed);
+        // Passing the same PC again doesn't overwrite it until the hook is re-armed
+        app.run_instructions(10, None);
+        assert_eq!(app.save_states[2], Some(saved));
+        app.pc_hooks[0].armed = true;
+        app.run_instructions(2, None);
+        assert_eq!(app.save_states[2].as_ref().unwrap().reg[0], 7);
+    }
+
+    #[test]
+    fn test_custom_visuals_follow_display_colors() {
+        let visuals = custom_visuals(&[1., 1., 0.], &[0., 0., 0.5]);
+        assert!(visuals.dark_mode);
+        assert_eq!(visuals.override_text_color, Some(Color32::from_rgb(255, 255, 0)));
+        assert_eq!(visuals.widgets.noninteractive.bg_fill, Color32::from_rgb(0, 0, 127));
+        assert!(!custom_visuals(&[0.; 3], &[1.; 3]).dark_mode);
+    }
+
+    #[test]
+    fn test_opcode_break_pauses_before_draw() {
+        let mut app = App::new();
+        app.load_hex("6001 6002 D015 1206").unwrap();
+        app.opcode_breaks.push(OpcodeBreak::Pattern("2NNN"));
+        app.opcode_breaks.push(OpcodeBreak::Pattern("DXYN"));
+        app.run_instructions(10, None);
+        assert!(app.pause_execution);
+        assert_eq!(app.chip8.pc, 0x204);
+        assert_eq!(app.chip8.reg[0], 2);
+        assert!(!app.chip8.redraw);
+        // Resuming runs the draw rather than stopping on it again
+        app.pause_execution = false;
+        app.run_instructions(1, None);
+        assert_eq!(app.chip8.pc, 0x206);
+        assert!(app.chip8.redraw);
+    }
+
+    #[test]
+    fn test_memory_bitmap() {
+        let memory = [0xF0, 0x90, 0x81, 0xFF];
+        let pixels = memory_bitmap(&memory, 1, 2, 2);
+        assert_eq!(pixels.len(), 32);
+        assert_eq!(pixels[..8], [true, false, false, true, false, false, false, false]);
+        assert_eq!(pixels[8..16], [true, false, false, false, false, false, false, true]);
+        assert_eq!(pixels[16..24], [true; 8]);
+        assert_eq!(pixels[24..], [false; 8]); // Past the end of memory
+    }
+
+    #[test]
+    fn test_memory_window_start() {
+        assert_eq!(memory_window_start(0x22A, 4096, 8), 0x1E0);
+        assert_eq!(memory_window_start(0x005, 4096, 8), 0x000);
+        assert_eq!(memory_window_start(0xFFF, 4096, 8), 0xF80);
+        assert_eq!(memory_window_start(0xFFFF, 4096, 8), 0xF80); // Past the end with a 16-bit I
+    }
+
+    #[test]
+    fn test_timers_frozen_while_paused() {
+        let mut app = App::new();
+        app.timers_run_while_paused = false;
+        app.pause_execution = true;
+        app.chip8.delay_timer = 10;
+        app.chip8.sound_timer = 10;
+        app.paused_frame(1);
+        assert_eq!((app.chip8.delay_timer, app.chip8.sound_timer), (10, 10));
+        app.timers_run_while_paused = true;
+        app.paused_frame(1);
+        assert_eq!((app.chip8.delay_timer, app.chip8.sound_timer), (9, 9));
+    }
+
+    #[test]
+    fn test_timers_follow_60hz_frames() {
+        let mut app = App::new();
+        app.chip8.load_vec(vec![0x7001, 0x1200]).unwrap();
+        app.chip8.delay_timer = 10;
+        // A 144Hz refresh with no 60Hz frame due yet runs instructions but leaves the timers alone
+        app.run_paced(4, 0, None);
+        assert_eq!((app.chip8.delay_timer, app.frame), (10, 0));
+        app.run_paced(4, 2, None);
+        assert_eq!((app.chip8.delay_timer, app.frame), (8, 2));
+        assert_eq!(app.chip8.reg[0], 4);
+    }
+
+    #[test]
+    fn test_audio_recorded_per_60hz_frame() {
+        let filename = std::env::temp_dir().join(format!("chip8_audio_{}.wav", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        let mut app = App::new();
+        app.chip8.load_vec(vec![0x1200]).unwrap();
+        app.start_audio_recording(filename);
+        app.run_paced(10, 0, None);
+        app.run_paced(10, 3, None);
+        app.pause_execution = true;
+        app.run_paced(10, 2, None);
+        assert_eq!(app.audio_recorder.as_ref().unwrap().frames, 3);
+        app.stop_audio_recording();
+        std::fs::remove_file(filename).ok();
+    }
+
+    #[test]
+    fn test_catch_up_interleaves_timers() {
+        let mut app = App::new();
+        // Copies the delay timer into V0, over and over
+        app.chip8.load_vec(vec![0xF007, 0x1200]).unwrap();
+        app.chip8.delay_timer = 10;
+        // Three frames of a catch-up burst each see the timer a tick further down
+        app.run_paced(6, 3, None);
+        assert_eq!((app.chip8.reg[0], app.chip8.delay_timer), (8, 7));
+    }
+
+    #[test]
+    fn test_timeline_marks_only_frames_that_drew() {
+        let mut app = App::new();
+        // Draws once, then spins
+        app.chip8.load_vec(vec![0xA000, 0xD011, 0x1204]).unwrap();
+        app.run_paced(6, 2, None);
+        let drew: Vec<bool> = app.timeline.frames.iter().map(|frame| frame.drew).collect();
+        assert_eq!(drew, vec![true, false]);
+        // The renderer still sees the draw
+        assert!(app.chip8.redraw);
     }
 }
@@ -0,0 +1,54 @@
+pub const SAMPLE_RATE: u32 = 44100;
+pub const FRAMES_PER_SEC: u32 = 60;
+const BEEP_FREQUENCY: f32 = 440.;
+const BEEP_VOLUME: f32 = 0.25;
+
+/// Generates the square-wave beep one frame at a time, keeping the phase continuous across frames
+pub struct Beeper {
+    phase: f32,
+    pub samples: Vec<f32>,
+}
+
+impl Beeper {
+    pub fn new() -> Self {
+        Beeper {
+            phase: 0.,
+            samples: vec![0.; (SAMPLE_RATE / FRAMES_PER_SEC) as usize],
+        }
+    }
+
+    pub fn generate_frame(&mut self, active: bool) {
+        let step = BEEP_FREQUENCY / SAMPLE_RATE as f32;
+        for sample in self.samples.iter_mut() {
+            *sample = match (active, self.phase < 0.5) {
+                (false, _) => 0.,
+                (true, true) => BEEP_VOLUME,
+                (true, false) => -BEEP_VOLUME,
+            };
+            self.phase = (self.phase + step).fract();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beeper_silent_when_inactive() {
+        let mut beeper = Beeper::new();
+        beeper.generate_frame(false);
+        assert_eq!(beeper.samples.len(), 735);
+        assert!(beeper.samples.iter().all(|sample| *sample == 0.));
+    }
+
+    #[test]
+    fn test_beeper_square_wave() {
+        let mut beeper = Beeper::new();
+        beeper.generate_frame(true);
+        assert!(beeper.samples.iter().all(|sample| sample.abs() == BEEP_VOLUME));
+        // About 100 samples per cycle at 440Hz, so it should have flipped by the middle of a cycle
+        assert_eq!(beeper.samples[0], BEEP_VOLUME);
+        assert_eq!(beeper.samples[60], -BEEP_VOLUME);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::chip8::Chip8;
+
+/// Instructions run inside one subroutine, split between its own code and the routines it called
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallNode {
+    // Where the routine starts, the address 2NNN called. The root is the program itself at 0x200.
+    pub entry: u16,
+    pub instructions: u64,
+    pub children: Vec<CallNode>,
+}
+
+impl CallNode {
+    fn new(entry: u16) -> Self {
+        CallNode { entry, instructions: 0, children: Vec::new() }
+    }
+
+    // Instructions run in this routine and everything it called
+    pub fn total(&self) -> u64 {
+        self.instructions + self.children.iter().map(|child| child.total()).sum::<u64>()
+    }
+
+    fn child(&mut self, entry: u16) -> &mut CallNode {
+        let index = match self.children.iter().position(|child| child.entry == entry) {
+            Some(index) => index,
+            None => {
+                self.children.push(CallNode::new(entry));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+}
+
+/// Builds a call tree by following 2NNN and 00EE, attributing each instruction to the routine it ran in
+pub struct CallTree {
+    pub root: CallNode,
+    // Entry addresses of the routines currently called, outermost first
+    path: Vec<u16>,
+}
+
+impl CallTree {
+    pub fn new() -> Self {
+        CallTree { root: CallNode::new(0x200), path: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        *self = CallTree::new();
+    }
+
+    // Records the instruction the last tick ran, then follows it into or out of a routine
+    pub fn record(&mut self, chip8: &Chip8) {
+        let mut node = &mut self.root;
+        for entry in &self.path {
+            node = node.child(*entry);
+        }
+        node.instructions += 1;
+
+        // Only follow calls and returns that actually changed the stack, so failed ones don't skew the tree
+        let depth = chip8.stack.len();
+        if chip8.opcode.code & 0xF000 == 0x2000 && depth == self.path.len() + 1 {
+            self.path.push(chip8.opcode.nnn);
+        }
+        self.path.truncate(depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_attribution() {
+        let mut chip8 = Chip8::new();
+        // 200: call 206, 202: jump to self, 206: two instructions and return
+        chip8.load_vec(vec![0x2206, 0x1202, 0x0000, 0x6001, 0x6102, 0x00EE]).unwrap();
+        let mut tree = CallTree::new();
+        for _ in 0..6 {
+            chip8.tick(None);
+            tree.record(&chip8);
+        }
+        assert_eq!(tree.root.children.len(), 1);
+        let routine = &tree.root.children[0];
+        assert_eq!(routine.entry, 0x206);
+        assert_eq!(routine.instructions, 3);
+        assert_eq!(tree.root.instructions, 3);
+        assert_eq!(tree.root.total(), 6);
+        tree.clear();
+        assert_eq!(tree.root.total(), 0);
+    }
+}
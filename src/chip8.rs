@@ -1,17 +1,33 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-#[path = "stack.rs"] mod stack;
+#[path = "stack.rs"] pub mod stack;
 use stack::Stack;
+#[path = "opcodes.rs"] pub mod opcodes;
+#[path = "quirks.rs"] pub mod quirks;
+use quirks::Quirks;
+#[path = "error.rs"] pub mod error;
+use error::Chip8Error;
 
 use log::{debug, error, trace, warn};
+use std::collections::{HashSet, VecDeque};
 use std::fs;
-use std::fs::File;
-use std::io::Read;
-use rand::Rng;
-use rand::rngs::ThreadRng;
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 const PIXEL_COUNT: usize = 32 * 64 * 3;
+// Room for the SUPER-CHIP high-res display. Low-res only uses the first 64 * 32 pixels.
+pub const DISPLAY_SIZE: usize = 128 * 64;
+pub const PC_HISTORY_LEN: usize = 64;
+// Bank switching (off by default, not part of any CHIP-8 standard): a ROM larger than memory is
+// split into banks of BANK_SIZE bytes, which are swapped in at 0x200. The last byte of memory is
+// then a register holding the current bank, and writing a bank number to it (FX33 or FX55 with I
+// at 0xFFF) swaps that bank in. Code that switches banks should sit at the same address in each.
+pub const BANK_SIZE: usize = BANK_REGISTER - 0x200;
+pub const BANK_REGISTER: usize = 0xFFF;
+// The bank register is one byte, so it can only select this many
+pub const MAX_BANKS: usize = 256;
 const FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -31,12 +47,11 @@ const FONT: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-fn op_implemented(pc: usize, opcode: u16, instruction: &str, description: &str) {
+fn op_implemented(pc: usize, opcode: u16) {
+    let (instruction, description) = opcodes::describe(opcode);
     debug!("I ({:#04x}) {:04X} | {} - {}", pc - 2, opcode, instruction, description);
-}
-
-fn op_unimplemented(pc: usize, opcode: u16, instruction: &str, description: &str) {
-    warn!("U ({:#04x}) {:04X} | {} - {}", pc - 2, opcode, instruction, description);
+    #[cfg(test)]
+    crate::coverage::record(instruction);
 }
 
 pub struct Opcode {
@@ -61,22 +76,246 @@ impl Opcode {
     }
 }
 
+/// An embedder's handler for opcodes the interpreter doesn't define. It matches when the opcode
+/// and pattern agree on every bit set in mask, e.g. pattern 0x0100 with mask 0xFF00 for 01NN.
+pub struct CustomOpcode {
+    pub pattern: u16,
+    pub mask: u16,
+    handler: Box<dyn FnMut(&mut Chip8)>,
+}
+
+impl CustomOpcode {
+    pub fn matches(&self, code: u16) -> bool {
+        code & self.mask == self.pattern & self.mask
+    }
+}
+
 pub struct Chip8 {
     pub pc: usize,
     pub opcode: Opcode,
+    // Prefer memory(), memory_mut() and patch() outside the core, this may become private
     pub memory: [u8; 4096],
-    pub display: [bool; 64 * 32],
+    pub display: [bool; DISPLAY_SIZE],
+    // SUPER-CHIP 128x64 mode, switched with 00FF and back with 00FE
+    pub hires: bool,
     pub stack: Stack,
     pub reg: [u8; 16],
     pub reg_i: u16,
     pub delay_timer: u8,
     pub sound_timer: u8,
     pub keypress: Option<u8>,
+    pub keys: [bool; 16],
+    // The most recently pressed key, for FX0A's polling quirk
+    pub last_key: Option<u8>,
     pub pixels: [u8; PIXEL_COUNT],
     pub redraw: bool,
-    pub rng: ThreadRng,
+    // The area of the display changed since take_dirty was last called
+    dirty: Option<DirtyRect>,
+    pub rng: StdRng,
+    // The seed the RNG was last started from, so a run's CXNN results can be reproduced
+    pub seed: u64,
+    // When set, CXNN uses this instead of a random number, for debugging
+    pub frozen_random: Option<u8>,
+    pub reset_rng: ResetRng,
     pub reg_read: Vec<usize>,
     pub reg_write: Vec<usize>,
+    pub rom: Vec<u8>,
+    pub pc_history: VecDeque<usize>,
+    // The registers and I before each instruction in pc_history ran, newest last
+    reg_history: VecDeque<([u8; 16], u16)>,
+    pub log_unknown_once: bool,
+    unknown_seen: HashSet<u16>,
+    pub sound_timer_slowdown: u32,
+    sound_timer_ticks: u32,
+    pub quirks: Quirks,
+    pub detect_idle_loop: bool,
+    // Set when the program jumps to itself, the usual way of halting
+    pub spinning: bool,
+    pub pc_policy: PcPolicy,
+    pub warn_pc_range: bool,
+    // Warn whenever 7XNN, 8XY4, 8XY5 or 8XY7 wraps past 255 or borrows below 0
+    pub warn_overflow: bool,
+    // Display offsets of pixels DXYN erased, kept while record_collisions is set for the caller to take
+    pub record_collisions: bool,
+    pub collisions: Vec<usize>,
+    // Set by PcPolicy::Halt, after which nothing runs until a reset
+    pub halted: bool,
+    // Halt on writes to the font and interpreter area below 0x200, which programs almost never mean to make
+    pub protect_low_memory: bool,
+    pub bank_switching: bool,
+    banks: Vec<Vec<u8>>,
+    pub bank: usize,
+    custom_opcodes: Vec<CustomOpcode>,
+    // Bit N is set while opcodes::OPCODES[N] is allowed to run, for isolating instructions when
+    // fuzzing or comparing against another emulator. Everything is enabled by default.
+    pub enabled_opcodes: u64,
+    pub disabled_opcode: DisabledOpcode,
+    // The first thing that went wrong in the instruction try_tick is running
+    error: Option<Chip8Error>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new()
+    }
+}
+
+/// A read-only view of the display, so renderers don't depend on how it's stored
+pub struct Frame<'a> {
+    pub width: usize,
+    pub height: usize,
+    pixels: &'a [bool],
+    // The pixels that changed since the display was last taken as drawn, if any did
+    pub dirty: Option<DirtyRect>,
+}
+
+/// A rectangle of display pixels that changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DirtyRect {
+    // The smallest rectangle covering both
+    pub fn union(&self, other: &DirtyRect) -> DirtyRect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        DirtyRect { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+impl Frame<'_> {
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x]
+    }
+
+    // Every pixel, row by row from the top left
+    pub fn pixels(&self) -> &[bool] {
+        self.pixels
+    }
+}
+
+/// What happened during a call to run_frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameResult {
+    pub instructions: u32,
+    pub redraw: bool,
+    // The part of the display the frame changed
+    pub dirty: Option<DirtyRect>,
+    pub sound: bool,
+}
+
+/// What happens when the PC leaves the program area, 0x200 to 0xFFF
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PcPolicy {
+    // Keep only the low 12 bits, so running off the top comes back round at 0x000
+    Wrap,
+    // Pull the PC back to the nearest end of the program area
+    Clamp,
+    // Stop executing and log an error until the machine is reset
+    Halt,
+}
+
+impl PcPolicy {
+    pub const ALL: [PcPolicy; 3] = [PcPolicy::Wrap, PcPolicy::Clamp, PcPolicy::Halt];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PcPolicy::Wrap => "Wrap",
+            PcPolicy::Clamp => "Clamp",
+            PcPolicy::Halt => "Halt",
+        }
+    }
+}
+
+/// What an opcode switched off in enabled_opcodes does when it runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisabledOpcode {
+    // Skip it as if it were a no-op
+    NoOp,
+    // Log an error and halt until the machine is reset
+    Error,
+}
+
+impl DisabledOpcode {
+    pub const ALL: [DisabledOpcode; 2] = [DisabledOpcode::NoOp, DisabledOpcode::Error];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisabledOpcode::NoOp => "No-op",
+            DisabledOpcode::Error => "Error",
+        }
+    }
+}
+
+/// What reset does to the RNG
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResetRng {
+    // Start again from the same seed, so a reset reproduces the same run
+    Reseed,
+    // Pick a new random seed
+    NewSeed,
+}
+
+impl ResetRng {
+    pub const ALL: [ResetRng; 2] = [ResetRng::Reseed, ResetRng::NewSeed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResetRng::Reseed => "Same seed",
+            ResetRng::NewSeed => "New seed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ResetRng> {
+        ResetRng::ALL.into_iter().find(|reset_rng| reset_rng.name() == name)
+    }
+}
+
+/// How a call to run_bounded ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunOutcome {
+    // The program jumped to itself, the usual way of halting, or PcPolicy::Halt stopped it
+    Halted { instructions: u64 },
+    TimedOut { instructions: u64 },
+}
+
+/// The 8 pixels a sprite byte draws, most significant bit on the left
+pub fn byte_pixels(byte: u8) -> [bool; 8] {
+    let mut pixels = [false; 8];
+    for (x, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = byte << x & 0b10000000 != 0;
+    }
+    pixels
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Folds bytes into a 64-bit FNV-1a hash, starting from FNV_OFFSET
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// A copy of the machine state that can be restored later
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub memory: [u8; 4096],
+    pub display: [bool; DISPLAY_SIZE],
+    pub hires: bool,
+    pub stack: Vec<u16>,
+    pub reg: [u8; 16],
+    pub reg_i: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
 }
 
 impl Chip8 {
@@ -87,23 +326,55 @@ impl Chip8 {
         for i in 0..FONT.len() {
             memory[i] = FONT[i];
         }
+        let seed = rand::random();
         // Return the Chip8
         Chip8 {
             pc: 0x200,
             memory,
             opcode: Opcode::new(0x0000),
-            display: [false; 64 * 32],
+            display: [false; DISPLAY_SIZE],
+            hires: false,
             stack: Stack::new(),
             reg: [0; 16],
             reg_i: 0,
             delay_timer: 0,
             sound_timer: 0,
             keypress: None,
+            keys: [false; 16],
+            last_key: None,
             pixels: [0; PIXEL_COUNT],
             redraw: false,
-            rng: rand::thread_rng(),
+            dirty: None,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            frozen_random: None,
+            reset_rng: ResetRng::Reseed,
             reg_read: Vec::new(),
             reg_write: Vec::new(),
+            rom: Vec::new(),
+            pc_history: VecDeque::with_capacity(PC_HISTORY_LEN),
+            reg_history: VecDeque::with_capacity(PC_HISTORY_LEN),
+            log_unknown_once: true,
+            unknown_seen: HashSet::new(),
+            sound_timer_slowdown: 1,
+            sound_timer_ticks: 0,
+            quirks: Quirks::default(),
+            detect_idle_loop: true,
+            spinning: false,
+            pc_policy: PcPolicy::Wrap,
+            warn_pc_range: false,
+            warn_overflow: false,
+            record_collisions: false,
+            collisions: Vec::new(),
+            halted: false,
+            protect_low_memory: false,
+            bank_switching: false,
+            banks: Vec::new(),
+            bank: 0,
+            custom_opcodes: Vec::new(),
+            enabled_opcodes: u64::MAX,
+            disabled_opcode: DisabledOpcode::NoOp,
+            error: None,
         }
     }
 
@@ -115,60 +386,519 @@ impl Chip8 {
         }
         self.pc = 0x200;
         self.opcode = Opcode::new(0x0000);
-        self.display = [false; 64 * 32];
+        self.display = [false; DISPLAY_SIZE];
+        self.hires = false;
         self.reg = [0;16];
         self.reg_i = 0;
         self.delay_timer = 0;
         self.sound_timer = 0;
+        self.sound_timer_ticks = 0;
         self.keypress = None;
+        self.keys = [false; 16];
+        self.last_key = None;
         self.pixels = [0;PIXEL_COUNT];
         self.redraw = false;
+        // The last program's pixels are gone, so whatever draws next needs the whole display redrawn
+        self.dirty = None;
+        self.mark_all_dirty();
         self.reg_read.clear();
         self.reg_write.clear();
+        self.rom.clear();
+        self.pc_history.clear();
+        self.reg_history.clear();
+        self.unknown_seen.clear();
+        self.collisions.clear();
+        self.spinning = false;
+        self.halted = false;
+        self.banks.clear();
+        self.bank = 0;
+        match self.reset_rng {
+            ResetRng::Reseed => self.rng = StdRng::seed_from_u64(self.seed),
+            ResetRng::NewSeed => self.seed_rng(rand::random()),
+        }
     }
 
-    pub fn load_rom(&mut self, filename: &str) {
+    pub fn load_rom(&mut self, filename: &str) -> Result<(), Chip8Error> {
         trace!("Loading ROM file '{}'", filename);
-        let mut file = File::open(&filename).expect("File doesn't exist");
-        let metadata = fs::metadata(&filename).expect("Unable to read metadata");
-        let filesize = metadata.len() as usize;
-        trace!("ROM file size is {} bytes", filesize);
-        let start = 0x200;
-        let end = start + filesize;
-        file.read_exact(&mut self.memory[start..end])
-            .expect("Buffer overflow");
+        let bytes = fs::read(filename)?;
+        trace!("ROM file size is {} bytes", bytes.len());
+        self.load_bytes(&bytes)
+    }
+
+    // Bytes of memory a ROM can fill, from 0x200 to the end
+    pub fn rom_space(&self) -> usize {
+        self.memory.len() - 0x200
+    }
+
+    // Copies a ROM into memory at 0x200, leaving memory alone if it doesn't fit
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), Chip8Error> {
+        if self.bank_switching {
+            let space = BANK_SIZE * MAX_BANKS;
+            if bytes.len() > space {
+                return Err(Chip8Error::RomTooLarge { size: bytes.len(), space });
+            }
+            self.banks = bytes.chunks(BANK_SIZE).map(|bank| bank.to_vec()).collect();
+            trace!("ROM split into {} banks", self.banks.len());
+            self.rom = bytes.to_vec();
+            self.select_bank(0);
+            return Ok(());
+        }
+        self.load_unbanked(bytes)
+    }
+
+    fn load_unbanked(&mut self, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let space = self.rom_space();
+        if bytes.len() > space {
+            return Err(Chip8Error::RomTooLarge { size: bytes.len(), space });
+        }
+        self.memory[0x200..0x200 + bytes.len()].copy_from_slice(bytes);
+        self.rom = bytes.to_vec();
+        Ok(())
+    }
+
+    // Loads a program given as opcodes straight into memory, even with bank switching on, failing
+    // like load_bytes if it doesn't fit
+    pub fn load_vec(&mut self, vector: Vec<u16>) -> Result<(), Chip8Error> {
+        let bytes: Vec<u8> = vector.iter().flat_map(|code| code.to_be_bytes()).collect();
+        self.load_unbanked(&bytes)
+    }
+
+    /// FNV-1a hash of the loaded ROM, used to identify it in reports
+    pub fn rom_hash(&self) -> u64 {
+        fnv1a(FNV_OFFSET, &self.rom)
+    }
+
+    /// FNV-1a hash of the registers, I, PC, stack, timers, memory and display, so two machines
+    /// can be compared at a glance. Equal states always give equal checksums.
+    pub fn state_checksum(&self) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET, &self.reg);
+        hash = fnv1a(hash, &self.reg_i.to_le_bytes());
+        hash = fnv1a(hash, &(self.pc as u16).to_le_bytes());
+        for element in self.stack.elements() {
+            hash = fnv1a(hash, &element.to_le_bytes());
+        }
+        hash = fnv1a(hash, &[self.delay_timer, self.sound_timer, self.hires as u8]);
+        hash = fnv1a(hash, &self.memory);
+        let display: Vec<u8> = self.frame().pixels().iter().map(|set| *set as u8).collect();
+        fnv1a(hash, &display)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            memory: self.memory,
+            display: self.display,
+            hires: self.hires,
+            stack: self.stack.elements().to_vec(),
+            reg: self.reg,
+            reg_i: self.reg_i,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        trace!("Restoring a snapshot");
+        self.pc = snapshot.pc;
+        self.memory = snapshot.memory;
+        self.display = snapshot.display;
+        self.hires = snapshot.hires;
+        self.stack = Stack::new();
+        for element in &snapshot.stack {
+            if self.stack.push(*element).is_err() {
+                warn!("Snapshot stack is too deep, dropping {:#05x}", element);
+            }
+        }
+        self.reg = snapshot.reg;
+        self.reg_i = snapshot.reg_i;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.opcode = self.fetch();
+        self.mark_all_dirty();
+        self.redraw = true;
+        self.spinning = false;
+        self.halted = false;
+    }
+
+    // Classic CHIP-8 addresses wrap at 12 bits, XO-CHIP uses all 16
+    fn address_mask(&self) -> usize {
+        if self.quirks.wide_index { 0xFFFF } else { 0x0FFF }
+    }
+
+    /// All 4K of memory. The font is at 0x000-0x04F and programs start at 0x200.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Unchecked access to all of memory, including the font. Use patch to keep the font intact.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// Writes bytes starting at an address, refusing writes that run past the end of memory or
+    /// touch the font, so FX29 sprites stay correct
+    pub fn patch(&mut self, address: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let end = address.checked_add(bytes.len()).filter(|end| *end <= self.memory.len())
+            .ok_or(Chip8Error::AddressOutOfRange(address))?;
+        if address < FONT.len() && !bytes.is_empty() {
+            return Err(Chip8Error::AddressOutOfRange(address));
+        }
+        self.memory[address..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn read_mem(&self, address: usize) -> u8 {
+        let address = address & self.address_mask();
+        match self.memory.get(address) {
+            Some(byte) => *byte,
+            None => {
+                warn!("Read from {:#06x} is outside memory", address);
+                0
+            }
+        }
+    }
+
+    pub fn bank_count(&self) -> usize {
+        self.banks.len()
+    }
+
+    // Swaps a bank into 0x200, leaving memory alone if there's no such bank
+    pub fn select_bank(&mut self, bank: usize) {
+        let Some(bytes) = self.banks.get(bank) else {
+            warn!("Bank {} selected but the ROM only has {}", bank, self.banks.len());
+            return;
+        };
+        self.memory[0x200..BANK_REGISTER].fill(0);
+        self.memory[0x200..0x200 + bytes.len()].copy_from_slice(bytes);
+        self.memory[BANK_REGISTER] = bank as u8;
+        self.bank = bank;
+    }
+
+    pub fn write_mem(&mut self, address: usize, value: u8) {
+        // A fault earlier in the same FX55 or FX33 drops the rest of its writes
+        if self.halted {
+            return;
+        }
+        let address = address & self.address_mask();
+        if self.bank_switching && address == BANK_REGISTER {
+            self.select_bank(value as usize);
+            return;
+        }
+        if self.protect_low_memory && address < 0x200 {
+            error!("Halting, write to protected address {:#05x} at {:#05x}", address, self.pc - 2);
+            self.halted = true;
+            self.fail(Chip8Error::ProtectedWrite { pc: self.pc - 2, address });
+            return;
+        }
+        match self.memory.get_mut(address) {
+            Some(byte) => *byte = value,
+            None => {
+                warn!("Write to {:#06x} is outside memory", address);
+                self.fail(Chip8Error::AddressOutOfRange(address));
+            }
+        }
+    }
+
+    // Blanks the screen without touching the rest of the machine
+    pub fn clear_display(&mut self) {
+        self.display.fill(false);
+        self.mark_all_dirty();
+        self.redraw = true;
+    }
+
+    // Width and height of the display in pixels
+    pub fn display_dimensions(&self) -> (usize, usize) {
+        if self.hires { (128, 64) } else { (64, 32) }
+    }
+
+    pub fn frame(&self) -> Frame<'_> {
+        let (width, height) = self.display_dimensions();
+        Frame { width, height, pixels: &self.display[..width * height], dirty: self.dirty }
+    }
+
+    // Grows the changed area to cover rect
+    fn mark_dirty(&mut self, rect: DirtyRect) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&rect),
+            None => rect,
+        });
+    }
+
+    // Marks the whole display at its current resolution as changed
+    fn mark_all_dirty(&mut self) {
+        let (width, height) = self.display_dimensions();
+        self.mark_dirty(DirtyRect { x: 0, y: 0, width, height });
+    }
+
+    // Asks for the whole display to be redrawn, for when how it's shown changes rather than its pixels
+    pub fn redraw_all(&mut self) {
+        self.redraw = true;
+        self.mark_all_dirty();
+    }
+
+    // Hands over the changed area and starts tracking afresh, for callers redrawing only what changed
+    pub fn take_dirty(&mut self) -> Option<DirtyRect> {
+        self.dirty.take()
+    }
+
+    // Switches between low-res and high-res. The pixel layout changes, so the display is cleared.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear_display();
+    }
+
+    // Where a sprite pixel lands on the display, or None if it's clipped off an edge
+    fn sprite_pixel_offset(&self, x: usize, y: usize) -> Option<usize> {
+        let (width, height) = self.display_dimensions();
+        let x = match x {
+            x if x < width => x,
+            x if self.quirks.wraps_horizontally() => x % width,
+            _ => return None,
+        };
+        let y = match y {
+            y if y < height => y,
+            y if self.quirks.wraps_vertically() => y % height,
+            _ => return None,
+        };
+        Some(y * width + x)
+    }
+
+    // Display offsets of the set pixels in one row of a sprite drawn at (vx, vy), skipping clipped ones
+    fn sprite_row_offsets(&self, vx: usize, vy: usize, sprite_y: usize) -> [Option<usize>; 8] {
+        let row = self.read_mem(self.reg_i as usize + sprite_y);
+        let mut offsets = [None; 8];
+        for (sprite_x, (offset, set)) in offsets.iter_mut().zip(byte_pixels(row)).enumerate() {
+            if set {
+                *offset = self.sprite_pixel_offset(vx + sprite_x, vy + sprite_y);
+            }
+        }
+        offsets
+    }
+
+    // The starting position always wraps, the edges decide what happens to the rest of the sprite
+    fn sprite_origin(&self, opcode: &Opcode) -> (usize, usize) {
+        let (width, height) = self.display_dimensions();
+        (self.reg[opcode.x] as usize % width, self.reg[opcode.y] as usize % height)
     }
 
-    pub fn load_vec(&mut self, vector: Vec<u16>) {
-        for i in 0..vector.len() {
-            self.memory[(i * 2) + 0x200] = ((vector[i] & 0xFF00) >> 8) as u8;
-            self.memory[(i * 2) + 0x200 + 1] = (vector[i] & 0x00FF) as u8;
+    // The display pixels the next instruction will flip, if it's a DXYN
+    pub fn pending_sprite(&self) -> Vec<usize> {
+        let opcode = self.next_opcode();
+        if opcode.code & 0xF000 != 0xD000 {
+            return Vec::new();
         }
+        let (vx, vy) = self.sprite_origin(&opcode);
+        (0..opcode.n).flat_map(|sprite_y| self.sprite_row_offsets(vx, vy, sprite_y)).flatten().collect()
+    }
+
+    // The last `count` instructions run, oldest first, each with the registers and I as they were
+    // just before it ran. Opcodes are read from memory now, so self-modified code shows its new form.
+    pub fn instruction_history(&self, count: usize) -> String {
+        // A restored bug report has PCs but no registers, so only entries with both are listed
+        let count = count.min(self.pc_history.len()).min(self.reg_history.len());
+        let pcs = self.pc_history.iter().skip(self.pc_history.len() - count);
+        let regs = self.reg_history.iter().skip(self.reg_history.len() - count);
+        pcs.zip(regs)
+            .map(|(pc, (reg, reg_i))| {
+                let code = self.opcode_at(*pc);
+                let reg: Vec<String> = reg.iter().map(|value| format!("{:02X}", value)).collect();
+                format!("{:03X} {:04X} {:<16} V0-VF {} I {:03X}\n", pc, code, opcodes::disassemble(code), reg.join(" "), reg_i)
+            })
+            .collect()
+    }
+
+    // Renders the display as rows of '#' (on) and '.' (off)
+    pub fn display_to_string(&self) -> String {
+        let frame = self.frame();
+        frame
+            .pixels()
+            .chunks(frame.width)
+            .map(|row| row.iter().map(|p| if *p { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
     pub fn fetch(&mut self) -> Opcode {
         trace!("Fetching the next opcode at {:#04x}", self.pc);
-        let left = self.memory[self.pc] as u16;
-        let right = self.memory[self.pc + 1] as u16;
-        Opcode::new(left << 8 | right)
+        Opcode::new(self.opcode_at(self.pc))
+    }
+
+    // The instruction the next tick will run, decoded without advancing the PC
+    pub fn next_opcode(&self) -> Opcode {
+        Opcode::new(self.opcode_at(self.pc))
+    }
+
+    pub fn opcode_at(&self, address: usize) -> u16 {
+        // The address may be odd after a jump, and the second byte wraps at the top of memory
+        let left = self.memory[address & 0xFFF] as u16;
+        let right = self.memory[(address + 1) & 0xFFF] as u16;
+        left << 8 | right
+    }
+
+    // Runs one instruction like tick, but reports the first error it hit rather than only logging it
+    pub fn try_tick(&mut self, keypress: Option<u8>) -> Result<(), Chip8Error> {
+        self.error = None;
+        self.tick(keypress);
+        self.error.take().map_or(Ok(()), Err)
+    }
+
+    fn fail(&mut self, error: Chip8Error) {
+        self.error.get_or_insert(error);
     }
 
     pub fn tick(&mut self, keypress: Option<u8>) {
+        if !self.check_pc() {
+            return;
+        }
         if !self.reg_read.is_empty() { self.reg_read.clear() };
         if !self.reg_write.is_empty() { self.reg_write.clear() };
+        if self.pc_history.len() == PC_HISTORY_LEN { self.pc_history.pop_front(); }
+        self.pc_history.push_back(self.pc);
+        if self.reg_history.len() == PC_HISTORY_LEN { self.reg_history.pop_front(); }
+        self.reg_history.push_back((self.reg, self.reg_i));
         self.opcode = self.fetch();
         self.pc += 2;
-        if self.delay_timer > 0 { self.delay_timer -= 1 };
-        if self.sound_timer > 0 { self.sound_timer -= 1 };
         self.keypress = keypress;
+        if keypress.is_some() {
+            self.last_key = keypress;
+        }
         self.execute();
     }
 
+    // Applies pc_policy if the PC has left the program area, returning whether execution can carry on
+    fn check_pc(&mut self) -> bool {
+        if self.halted {
+            return false;
+        }
+        if (0x200..=0xFFF).contains(&self.pc) {
+            return true;
+        }
+        if self.warn_pc_range {
+            warn!("PC {:#05x} is outside the program area", self.pc);
+        }
+        match self.pc_policy {
+            PcPolicy::Wrap => self.pc &= 0xFFF,
+            PcPolicy::Clamp => self.pc = self.pc.clamp(0x200, 0xFFE),
+            PcPolicy::Halt => {
+                error!("Halting, PC {:#05x} is outside the program area", self.pc);
+                self.halted = true;
+                return false;
+            }
+        }
+        true
+    }
+
+    // Runs one 60Hz frame with the given keys held, for fuzzing and other headless use.
+    // Seed the RNG first with seed_rng to make it fully deterministic.
+    pub fn run_frame(&mut self, keys: &[bool; 16], instructions: u32) -> FrameResult {
+        self.keys = *keys;
+        self.redraw = false;
+        self.dirty = None;
+        for _ in 0..instructions {
+            self.tick(None);
+        }
+        self.tick_timers();
+        FrameResult {
+            instructions,
+            redraw: self.redraw,
+            dirty: self.take_dirty(),
+            sound: self.sound_timer > 0,
+        }
+    }
+
+    /// Runs a ROM from power-on with a fixed seed, one frame per set of keys and then with no keys
+    /// held once they run out, for max_instructions in all. Returns the final state checksum, which
+    /// only changes between runs with the same inputs if the emulator's behaviour has.
+    pub fn golden_run(rom: &[u8], seed: u64, frames: impl IntoIterator<Item = [bool; 16]>, instructions_per_frame: u32, max_instructions: u64) -> Result<u64, Chip8Error> {
+        let mut chip8 = Chip8::new();
+        chip8.seed_rng(seed);
+        chip8.load_bytes(rom)?;
+        let mut frames = frames.into_iter();
+        let mut instructions = 0;
+        while instructions < max_instructions {
+            let keys = frames.next().unwrap_or([false; 16]);
+            let count = (max_instructions - instructions).min(instructions_per_frame as u64) as u32;
+            chip8.run_frame(&keys, count);
+            instructions += count as u64;
+        }
+        Ok(chip8.state_checksum())
+    }
+
+    // Runs until the program halts in an idle loop, or gives up after max_instructions or max_time,
+    // so headless runs of ROMs that never halt still finish
+    pub fn run_bounded(&mut self, max_instructions: u64, max_time: Option<Duration>) -> RunOutcome {
+        let start = Instant::now();
+        for instructions in 0..max_instructions {
+            if self.spinning || self.halted {
+                return RunOutcome::Halted { instructions };
+            }
+            // Checking the clock every instruction would slow the run down
+            if instructions % 1000 == 0 && max_time.is_some_and(|limit| start.elapsed() >= limit) {
+                return RunOutcome::TimedOut { instructions };
+            }
+            self.tick(None);
+        }
+        match self.spinning || self.halted {
+            true => RunOutcome::Halted { instructions: max_instructions },
+            false => RunOutcome::TimedOut { instructions: max_instructions },
+        }
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // Called at 60Hz, independently of how many instructions run per frame
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 { self.delay_timer -= 1 };
+        // The sound timer can be slowed down on its own to make beeps easier to hear
+        self.sound_timer_ticks += 1;
+        if self.sound_timer_ticks >= self.sound_timer_slowdown {
+            self.sound_timer_ticks = 0;
+            if self.sound_timer > 0 { self.sound_timer -= 1 };
+        }
+    }
+
+    pub fn press_key(&mut self, key: u8) {
+        match self.keys.get_mut(key as usize) {
+            Some(state) => {
+                *state = true;
+                self.last_key = Some(key);
+            }
+            None => warn!("Ignoring press of invalid key {:#x}", key),
+        }
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        match self.keys.get_mut(key as usize) {
+            Some(state) => *state = false,
+            None => warn!("Ignoring release of invalid key {:#x}", key),
+        }
+    }
+
+    pub fn is_key_down(&self, key: u8) -> bool {
+        if self.quirks.single_key {
+            let first_held = self.keys.iter().position(|down| *down).map(|key| key as u8);
+            return self.keypress.or(first_held) == Some(key);
+        }
+        self.keys.get(key as usize).copied().unwrap_or(false) || self.keypress == Some(key)
+    }
+
     pub fn execute(&mut self) {
+        if self.enabled_opcodes != u64::MAX && !self.opcode_enabled(self.opcode.code) {
+            self.op_disabled();
+            return;
+        }
         match self.opcode.code & 0xF000 {
             0x0000 => match self.opcode.code & 0x0FFF {
+                0x00C0..=0x00CF => self.op_00cn(),
                 0x00E0 => self.op_00e0(),
                 0x00EE => self.op_00ee(),
+                0x00FB => self.op_00fb(),
+                0x00FC => self.op_00fc(),
+                0x00FE => self.op_00fe(),
+                0x00FF => self.op_00ff(),
                 _ => self.op_0nnn(),
             },
             0x1000 => self.op_1nnn(),
@@ -188,7 +918,7 @@ impl Chip8 {
                 0x0006 => self.op_8xy6(),
                 0x0007 => self.op_8xy7(),
                 0x000E => self.op_8xye(),
-                _ => error!("Unknown opcode {:04X}", self.opcode.code),
+                _ => self.op_unknown(),
             },
             0x9000 => self.op_9xy0(),
             0xA000 => self.op_annn(),
@@ -198,7 +928,7 @@ impl Chip8 {
             0xE000 => match self.opcode.code & 0x00FF {
                 0x009E => self.op_ex9e(),
                 0x00A1 => self.op_exa1(),
-                _ => error!("Unknown opcode {:04X}", self.opcode.code),
+                _ => self.op_unknown(),
             },
             0xF000 => match self.opcode.code & 0x00FF {
                 0x0007 => self.op_fx07(),
@@ -210,49 +940,172 @@ impl Chip8 {
                 0x0033 => self.op_fx33(),
                 0x0055 => self.op_fx55(),
                 0x0065 => self.op_fx65(),
-                _ => error!("Unknown opcode {:04X}", self.opcode.code),
+                _ => self.op_unknown(),
             },
-            _ => error!("Unknown opcode {:04X}", self.opcode.code),
+            _ => self.op_unknown(),
+        }
+    }
+
+    // Unknown opcodes have no bit, so they're never disabled
+    pub fn opcode_enabled(&self, code: u16) -> bool {
+        opcodes::index(code).is_none_or(|index| self.enabled_opcodes & 1 << index != 0)
+    }
+
+    // Switches an opcode on or off by its pattern, e.g. "8XY4"
+    pub fn set_opcode_enabled(&mut self, pattern: &str, enabled: bool) -> Result<(), String> {
+        let index = opcodes::OPCODES.iter().position(|info| info.pattern == pattern)
+            .ok_or(format!("Unknown opcode pattern '{}'", pattern))?;
+        if enabled {
+            self.enabled_opcodes |= 1 << index;
+        } else {
+            self.enabled_opcodes &= !(1 << index);
+        }
+        Ok(())
+    }
+
+    fn op_disabled(&mut self) {
+        match self.disabled_opcode {
+            DisabledOpcode::NoOp => trace!("Skipping disabled opcode {:04X}", self.opcode.code),
+            DisabledOpcode::Error => {
+                error!("Halting, opcode {:04X} at {:#05x} is disabled", self.opcode.code, self.pc - 2);
+                self.halted = true;
+            }
+        }
+    }
+
+    /// Registers a handler for opcodes that would otherwise be unknown, or 0NNN machine code calls,
+    /// which is where homebrew usually puts its own. The first match registered wins. The handler
+    /// runs after the PC has moved past the opcode, which is in opcode.
+    pub fn register_custom_opcode(&mut self, pattern: u16, mask: u16, handler: impl FnMut(&mut Chip8) + 'static) {
+        self.custom_opcodes.push(CustomOpcode { pattern, mask, handler: Box::new(handler) });
+    }
+
+    // Runs the custom handler for the current opcode, returning whether there was one
+    fn run_custom_opcode(&mut self) -> bool {
+        let code = self.opcode.code;
+        let Some(index) = self.custom_opcodes.iter().position(|custom| custom.matches(code)) else {
+            return false;
+        };
+        // Taken out while it runs, since it needs the whole machine
+        let mut handlers = std::mem::take(&mut self.custom_opcodes);
+        (handlers[index].handler)(self);
+        handlers.append(&mut self.custom_opcodes);
+        self.custom_opcodes = handlers;
+        true
+    }
+
+    // Logs an opcode nothing handles, only the first time each one runs unless log_unknown_once is off
+    fn op_unknown(&mut self) {
+        if self.run_custom_opcode() {
+            return;
         }
+        if !self.log_unknown_once || self.unknown_seen.insert(self.opcode.code) {
+            error!("Unknown opcode {:04X} at {:#05x}", self.opcode.code, self.pc - 2);
+        }
+        self.fail(Chip8Error::UnknownOpcode(self.opcode.code));
     }
 
     fn op_0nnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "0NNN", "Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs.");
+        if self.run_custom_opcode() {
+            return;
+        }
+        op_implemented(self.pc, self.opcode.code);
         self.pc = self.opcode.nnn as usize;
     }
     fn op_00e0(&mut self) {
-        op_implemented(self.pc, 0x00E0, "00EE", "Clears the screen.");
-        self.display.fill(false);
+        op_implemented(self.pc, self.opcode.code);
+        self.clear_display();
+    }
+    // SUPER-CHIP 1.1 always scrolled in high-res pixels, so in low-res it only moved half as far.
+    // Octo, XO-CHIP and most modern interpreters scroll whole low-res pixels, which is the default.
+    // The low-res display has no half pixels, so odd distances round down when halving.
+    fn scroll_distance(&self, distance: usize) -> usize {
+        if self.quirks.half_scroll_lowres && !self.hires {
+            distance / 2
+        } else {
+            distance
+        }
+    }
+    // Moves the whole display, filling the pixels scrolled in with blanks
+    fn scroll_display(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.display_dimensions();
+        let old = self.display;
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x as isize - dx, y as isize - dy);
+                let inside = (0..width as isize).contains(&from_x) && (0..height as isize).contains(&from_y);
+                self.display[y * width + x] = inside && old[from_y as usize * width + from_x as usize];
+            }
+        }
+        self.mark_all_dirty();
+        self.redraw = true;
+    }
+    fn op_00cn(&mut self) {
+        op_implemented(self.pc, self.opcode.code);
+        let distance = self.scroll_distance(self.opcode.n);
+        self.scroll_display(0, distance as isize);
+    }
+    fn op_00fb(&mut self) {
+        op_implemented(self.pc, self.opcode.code);
+        let distance = self.scroll_distance(self.quirks.horizontal_scroll);
+        self.scroll_display(distance as isize, 0);
+    }
+    fn op_00fc(&mut self) {
+        op_implemented(self.pc, self.opcode.code);
+        let distance = self.scroll_distance(self.quirks.horizontal_scroll);
+        self.scroll_display(-(distance as isize), 0);
+    }
+    fn op_00fe(&mut self) {
+        op_implemented(self.pc, self.opcode.code);
+        self.set_hires(false);
+    }
+    fn op_00ff(&mut self) {
+        op_implemented(self.pc, self.opcode.code);
+        self.set_hires(true);
     }
     fn op_00ee(&mut self) {
-        op_implemented(self.pc, 0x00EE, "00EE", "Returns from a subroutine.");
-        self.pc = self.stack.pop() as usize;
+        op_implemented(self.pc, self.opcode.code);
+        match self.stack.pop() {
+            Ok(address) => self.pc = address as usize,
+            Err(e) => {
+                error!("Unable to return at {:#05x}: {:?}", self.pc - 2, e);
+                self.fail(e.into());
+            }
+        }
     }
     fn op_1nnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "1NNN", "Jumps to address NNN.");
+        op_implemented(self.pc, self.opcode.code);
+        if self.detect_idle_loop && self.opcode.nnn as usize == self.pc - 2 {
+            self.spinning = true;
+        }
         self.pc = self.opcode.nnn as usize;
     }
     fn op_2nnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "2NNN", "Calls subroutine at NNN.");
-        self.stack.push(self.pc as u16);
-        self.pc = self.opcode.nnn as usize;
+        op_implemented(self.pc, self.opcode.code);
+        match self.stack.push(self.pc as u16) {
+            Ok(()) => self.pc = self.opcode.nnn as usize,
+            Err(e) => {
+                error!("Unable to call {:#05x}: {:?}", self.opcode.nnn, e);
+                self.fail(e.into());
+            }
+        }
     }
     fn op_3xnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "3XNN", "Skips the next instruction if VX equals NN. (Usually the next instruction is a jump to skip a code block)");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         if self.reg[self.opcode.x] == self.opcode.nn {
             self.pc += 2;
         }
     }
     fn op_4xnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "4NNN", "Skips the next instruction if VX does not equal NN. (Usually the next instruction is a jump to skip a code block);");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         if self.reg[self.opcode.x] != self.opcode.nn {
             self.pc += 2;
         }
     }
     fn op_5xy0(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "5XY0", "Skips the next instruction if VX equals VY. (Usually the next instruction is a jump to skip a code block);");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         self.reg_read.push(self.opcode.y);
         if self.reg[self.opcode.x] == self.reg[self.opcode.y] {
@@ -260,55 +1113,57 @@ impl Chip8 {
         }
     }
     fn op_6xnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "6XNN", "Sets VX to NN.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write.push(self.opcode.x);
         self.reg[self.opcode.x] = self.opcode.nn;
     }
     fn op_7xnn(&mut self) {
-        op_implemented(
-            self.pc,
-            self.opcode.code,
-            "7XNN",
-            "Adds NN to VX. (Carry flag is not changed);",
-        );
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write.push(self.opcode.x);
-        self.reg[self.opcode.x] = self.reg[self.opcode.x].wrapping_add(self.opcode.nn);
+        let vx = self.reg[self.opcode.x];
+        let (result, carry) = vx.overflowing_add(self.opcode.nn);
+        self.reg[self.opcode.x] = result;
+        if carry {
+            self.warn_wrapped(format!("V{:X} ({:#04x}) + {:#04x}", self.opcode.x, vx, self.opcode.nn), result);
+        }
     }
     fn op_8xy0(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "8XY0", "Sets VX to the value of VY.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write.push(self.opcode.x);
         self.reg_read.push(self.opcode.y);
         self.reg[self.opcode.x] = self.reg[self.opcode.y];
     }
+    // The COSMAC VIP ran the logical ops through its ALU in a way that left VF zeroed afterwards,
+    // and some ROMs rely on it. CHIP-48 and SUPER-CHIP rewrote them and leave VF alone.
+    fn logical_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.reg_write.push(0xF);
+            self.reg[0xF] = 0;
+        }
+    }
     fn op_8xy1(&mut self) {
-        op_unimplemented(
-            self.pc,
-            self.opcode.code,
-            "8XY1",
-            "Sets VX to VX or VY. (Bitwise OR operation);",
-        );
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.y);
+        self.reg_write.push(self.opcode.x);
+        self.reg[self.opcode.x] |= self.reg[self.opcode.y];
+        self.logical_vf_reset();
     }
     fn op_8xy2(&mut self) {
-        op_implemented(
-            self.pc,
-            self.opcode.code,
-            "8XY2",
-            "Sets VX to VX and VY. (Bitwise AND operation);",
-        );
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.y);
         self.reg_write.push(self.opcode.x);
         self.reg[self.opcode.x] &= self.reg[self.opcode.y];
+        self.logical_vf_reset();
     }
     fn op_8xy3(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "8XY3", "Sets VX to VX xor VY.");
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.y);
+        self.reg_write.push(self.opcode.x);
+        self.reg[self.opcode.x] ^= self.reg[self.opcode.y];
+        self.logical_vf_reset();
     }
     fn op_8xy4(&mut self) {
-        op_implemented(
-            self.pc,
-            self.opcode.code,
-            "8XY4",
-            "Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not.",
-        );
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.y);
         self.reg_write.push(self.opcode.x);
         let vx = self.reg[self.opcode.x];
@@ -316,148 +1171,224 @@ impl Chip8 {
         let (result, carry) = vx.overflowing_add(vy);
         self.reg[self.opcode.x] = result;
         self.reg[0xF] = carry as u8;
-        if carry { self.reg_write.push(0xF) };
+        self.reg_write.push(0xF);
+        if carry {
+            self.warn_wrapped(format!("V{:X} ({:#04x}) + V{:X} ({:#04x})", self.opcode.x, vx, self.opcode.y, vy), result);
+        }
     }
     fn op_8xy5(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "8XY5", "VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there is not.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.y);
         self.reg_write.push(self.opcode.x);
         let vx = self.reg[self.opcode.x];
         let vy = self.reg[self.opcode.y];
         let (result, carry) = vx.overflowing_sub(vy);
         self.reg[self.opcode.x] = result;
+        // VF is 1 when there's no borrow, the opposite of overflowing_sub's flag. 8XY7 does the same.
         self.reg[0xF] = !carry as u8;
-        if !carry { self.reg_write.push(0xF) };
+        self.reg_write.push(0xF);
+        if carry {
+            self.warn_wrapped(format!("V{:X} ({:#04x}) - V{:X} ({:#04x})", self.opcode.x, vx, self.opcode.y, vy), result);
+        }
     }
     fn op_8xy6(&mut self) {
-        op_unimplemented(
-            self.pc,
-            self.opcode.code,
-            "8XY6",
-            "Stores the least significant bit of VX in VF and then shifts VX to the right by 1.",
-        );
+        op_implemented(self.pc, self.opcode.code);
+        let source = if self.quirks.shift_vx { self.opcode.x } else { self.opcode.y };
+        self.reg_read.push(source);
+        self.reg_write.push(self.opcode.x);
+        self.reg_write.push(0xF);
+        let value = self.reg[source];
+        self.reg[self.opcode.x] = value >> 1;
+        // VF last, so the flag wins when X is F
+        self.reg[0xF] = value & 1;
     }
     fn op_8xy7(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "8XY7", "Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there is not.");
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.y);
+        self.reg_write.push(self.opcode.x);
+        let vx = self.reg[self.opcode.x];
+        let vy = self.reg[self.opcode.y];
+        let (result, carry) = vy.overflowing_sub(vx);
+        self.reg[self.opcode.x] = result;
+        self.reg[0xF] = !carry as u8;
+        self.reg_write.push(0xF);
+        if carry {
+            self.warn_wrapped(format!("V{:X} ({:#04x}) - V{:X} ({:#04x})", self.opcode.y, vy, self.opcode.x, vx), result);
+        }
+    }
+    // Explains an add that carried past 255 or a subtraction that borrowed, when warn_overflow is on
+    fn warn_wrapped(&self, operation: String, result: u8) {
+        if self.warn_overflow {
+            warn!("{:04X} at {:#05x} wrapped: {} = {:#04x}", self.opcode.code, self.pc - 2, operation, result);
+        }
     }
     fn op_8xye(&mut self) {
-        op_unimplemented(
-            self.pc,
-            self.opcode.code,
-            "8XYE",
-            "Stores the most significant bit of VX in VF and then shifts VX to the left by 1.",
-        );
+        op_implemented(self.pc, self.opcode.code);
+        let source = if self.quirks.shift_vx { self.opcode.x } else { self.opcode.y };
+        self.reg_read.push(source);
+        self.reg_write.push(self.opcode.x);
+        self.reg_write.push(0xF);
+        let value = self.reg[source];
+        self.reg[self.opcode.x] = value << 1;
+        self.reg[0xF] = value >> 7;
     }
     fn op_9xy0(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "9XY0", "Skips the next instruction if VX does not equal VY. (Usually the next instruction is a jump to skip a code block);");
-    }
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.x);
+        self.reg_read.push(self.opcode.y);
+        if self.reg[self.opcode.x] != self.reg[self.opcode.y] {
+            self.pc += 2;
+        }
+    }
     fn op_annn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "ANNN", "Sets I to the address NNN.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_i = self.opcode.nnn;
     }
     fn op_bnnn(&mut self) {
-        op_unimplemented(
-            self.pc,
-            self.opcode.code,
-            "BNNN",
-            "Jumps to the address NNN plus V0.",
-        );
+        op_implemented(self.pc, self.opcode.code);
+        let offset = if self.quirks.jump_vx { self.opcode.x } else { 0 };
+        self.reg_read.push(offset);
+        self.pc = self.opcode.nnn as usize + self.reg[offset] as usize;
     }
     fn op_cxnn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "CXNN", "Sets VX to the result of a bitwise and operation on a random number (Typically: 0 to 255) and NN.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write.push(self.opcode.x);
-        self.reg[self.opcode.x] = self.rng.gen_range(0..=255) & self.opcode.nn;
+        let random = match self.frozen_random {
+            Some(value) => value,
+            None => self.rng.gen_range(0..=255),
+        };
+        self.reg[self.opcode.x] = random & self.opcode.nn;
     }
     fn op_dxyn(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "DXYN","Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels. Each row of 8 pixels is read as bit-coded starting from memory location I; I value does not change after the execution of this instruction. As described above, VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.y);
         self.reg_read.push(self.opcode.x);
-        let vx = self.reg[self.opcode.x] as usize;
-        let vy = self.reg[self.opcode.y] as usize;
+        let (width, height) = self.display_dimensions();
+        let (vx, vy) = self.sprite_origin(&self.opcode);
+        let mut flipped: Option<DirtyRect> = None;
 
+        let mut collided_rows = 0;
+        let mut clipped_rows = 0;
         for sprite_y in 0..self.opcode.n {
-            for sprite_x in 0..8 {
-                if self.memory[self.reg_i as usize + sprite_y] << sprite_x & 0b10000000 == 128 {
-                    let offset = ((vy + sprite_y) * 64) + (vx + sprite_x);
-                    if offset < 64 * 32 {
-                        self.reg[0xF] = self.display[offset] as u8; // Set VF
-                        self.display[offset] = !self.display[offset]; // Flip pixel
-                    }
+            if vy + sprite_y >= height && !self.quirks.wraps_vertically() {
+                clipped_rows += 1;
+            }
+            let mut collided = false;
+            for offset in self.sprite_row_offsets(vx, vy, sprite_y).into_iter().flatten() {
+                if self.display[offset] && self.record_collisions {
+                    self.collisions.push(offset);
                 }
+                collided |= self.display[offset];
+                self.display[offset] = !self.display[offset]; // Flip pixel
+                let pixel = DirtyRect { x: offset % width, y: offset / width, width: 1, height: 1 };
+                flipped = Some(flipped.map_or(pixel, |rect| rect.union(&pixel)));
             }
+            collided_rows += collided as u8;
+        }
+        self.reg_write.push(0xF);
+        // SUPER-CHIP 1.1 counts the rows that collided or fell off the bottom in high-res,
+        // everything else just flags whether anything collided
+        self.reg[0xF] = if self.hires && self.quirks.vf_clipped_rows {
+            collided_rows + clipped_rows
+        } else {
+            (collided_rows > 0) as u8
+        };
+        if let Some(rect) = flipped {
+            self.mark_dirty(rect);
         }
         self.redraw = true;
     }
     fn op_ex9e(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "EX9E", "Skips the next instruction if the key stored in VX is pressed. (Usually the next instruction is a jump to skip a code block);");
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.x);
+        if self.is_key_down(self.reg[self.opcode.x]) {
+            self.pc += 2;
+        }
     }
     fn op_exa1(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "EXA1", "Skips the next instruction if the key stored in VX is not pressed. (Usually the next instruction is a jump to skip a code block);");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
-        match self.keypress {
-            Some(key) => {
-                if key != self.reg[self.opcode.x] {
-                    self.pc += 2
-                }
-            }
-            None => (),
+        if !self.is_key_down(self.reg[self.opcode.x]) {
+            self.pc += 2;
         }
     }
     fn op_fx07(&mut self) {
-        op_implemented(
-            self.pc,
-            self.opcode.code,
-            "FX07",
-            "Sets VX to the value of the delay timer.",
-        );
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write.push(self.opcode.x);
         self.reg[self.opcode.x] = self.delay_timer;
     }
+    // Waits for a key by running again until one is down, unless the polling quirk is on
     fn op_fx0a(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "FX0A", "A key press is awaited, and then stored in VX. (Blocking Operation. All instruction halted until next key event);");
+        op_implemented(self.pc, self.opcode.code);
+        let held = self.keypress.or_else(|| self.keys.iter().position(|down| *down).map(|key| key as u8));
+        match held {
+            Some(key) => {
+                self.reg[self.opcode.x] = key;
+                self.reg_write.push(self.opcode.x);
+            }
+            None if self.quirks.fx0a_polling => {
+                if let Some(key) = self.last_key {
+                    self.reg[self.opcode.x] = key;
+                    self.reg_write.push(self.opcode.x);
+                }
+            }
+            None => self.pc -= 2,
+        }
     }
     fn op_fx15(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "FX15", "Sets the delay timer to VX.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         self.delay_timer = self.reg[self.opcode.x];
     }
     fn op_fx18(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "FX18", "Sets the sound timer to VX.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
-        self.sound_timer = self.reg[self.opcode.x];
+        let value = self.reg[self.opcode.x];
+        self.sound_timer = if self.quirks.sound_floor && value < 2 { 0 } else { value };
     }
     fn op_fx1e(&mut self) {
-        op_unimplemented(
-            self.pc,
-            self.opcode.code,
-            "FX1E",
-            "Adds VX to I. VF is not affected.",
-        );
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read.push(self.opcode.x);
+        let vx = self.reg[self.opcode.x];
+        // Whether the add carries out of the 12-bit address, worked out before I changes (or wraps
+        // round 16 bits) so an I already past 0xFFF under wide_index doesn't always count
+        let crossed = (self.reg_i & 0xFFF) as u32 + vx as u32 > 0xFFF;
+        self.reg_i = self.reg_i.wrapping_add(vx as u16);
+        if self.quirks.index_overflow_vf {
+            self.reg_write.push(0xF);
+            self.reg[0xF] = crossed as u8;
+        }
     }
     fn op_fx29(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "FX29", "Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         self.reg_i = 5 * self.reg[self.opcode.x] as u16;
     }
     fn op_fx33(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "FX33", "Stores the binary-coded decimal representation of VX, with the most significant of three digits at the address in I, the middle digit at I plus 1, and the least significant digit at I plus 2. (In other words, take the decimal representation of VX, place the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.);");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_read.push(self.opcode.x);
         let hundreds = self.reg[self.opcode.x] / 100 % 10;
         let tens = self.reg[self.opcode.x] / 10 % 10;
         let ones = self.reg[self.opcode.x] % 10;
-        self.memory[self.reg_i as usize] = hundreds;
-        self.memory[self.reg_i as usize + 1] = tens;
-        self.memory[self.reg_i as usize + 2] = ones;
+        self.write_mem(self.reg_i as usize, hundreds);
+        self.write_mem(self.reg_i as usize + 1, tens);
+        self.write_mem(self.reg_i as usize + 2, ones);
     }
     fn op_fx55(&mut self) {
-        op_unimplemented(self.pc, self.opcode.code, "FX55", "Stores from V0 to VX (including VX) in memory, starting at address I. The offset from I is increased by 1 for each value written, but I itself is left unmodified.");
+        op_implemented(self.pc, self.opcode.code);
+        self.reg_read = (0..=self.opcode.x).collect();
+        for i in 0..=self.opcode.x {
+            self.write_mem(self.reg_i as usize + i, self.reg[i]);
+        }
+        self.reg_i = self.reg_i.wrapping_add(self.quirks.load_store.increment(self.opcode.x));
     }
     fn op_fx65(&mut self) {
-        op_implemented(self.pc, self.opcode.code, "FX65", "Fills from V0 to VX (including VX) with values from memory, starting at address I. The offset from I is increased by 1 for each value written, but I itself is left unmodified.");
+        op_implemented(self.pc, self.opcode.code);
         self.reg_write = vec![0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]; // TODO: Find a way to do with programmatically
         for i in 0..=self.opcode.x {
-            self.reg[i] = self.memory[self.reg_i as usize + i];
+            self.reg[i] = self.read_mem(self.reg_i as usize + i);
         }
+        self.reg_i = self.reg_i.wrapping_add(self.quirks.load_store.increment(self.opcode.x));
     }
 }
 
@@ -475,6 +1406,230 @@ mod tests {
         assert_eq!(chip8.keypress, None);
     }
 
+    #[test]
+    fn test_record_collisions() {
+        let program = vec![0xA000, 0x6102, 0xD101, 0x6104, 0xD101];
+        let mut plain = Chip8::new();
+        let mut recording = Chip8::new();
+        recording.record_collisions = true;
+        for chip8 in [&mut plain, &mut recording] {
+            chip8.load_vec(program.clone()).unwrap();
+            for _ in 0..program.len() {
+                chip8.tick(None);
+            }
+        }
+        // The font's F0 drawn at x 2 then x 4 overlaps in columns 4 and 5
+        assert_eq!(recording.collisions, vec![4, 5]);
+        assert!(plain.collisions.is_empty());
+        assert_eq!(recording.display, plain.display);
+        assert_eq!(recording.reg[0xF], 1);
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut chip8 = Chip8::new();
+        chip8.patch(0x300, &[1, 2, 3]).unwrap();
+        assert_eq!(chip8.memory()[0x300..0x303], [1, 2, 3]);
+        chip8.patch(0xFFE, &[4, 5]).unwrap();
+        assert!(chip8.patch(0xFFF, &[6, 7]).is_err());
+        assert!(chip8.patch(usize::MAX, &[6]).is_err());
+        assert!(chip8.patch(0x04F, &[6]).is_err());
+        assert_eq!(chip8.memory()[0xFFF], 5);
+        assert_eq!(chip8.memory()[..FONT.len()], FONT);
+        chip8.memory_mut()[0x000] = 0;
+        assert_eq!(chip8.memory()[0x000], 0);
+    }
+
+    #[test]
+    fn test_instruction_history() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6105, 0x7101, 0xA123, 0x1206]).unwrap();
+        for _ in 0..5 {
+            chip8.tick(None);
+        }
+        let history = chip8.instruction_history(4);
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("202 7101 ADD V1, 0x01"));
+        assert!(lines[0].ends_with(" V0-VF 00 05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I 000"));
+        assert!(lines[1].starts_with("204 A123 "));
+        assert!(lines[2].starts_with("206 1206 "));
+        assert!(lines[3].starts_with("206 1206 "));
+        assert!(lines[3].ends_with(" V0-VF 00 06 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I 123"));
+        assert_eq!(chip8.instruction_history(100).lines().count(), 5);
+    }
+
+    #[test]
+    fn test_byte_pixels() {
+        assert_eq!(byte_pixels(0b1010_0001), [true, false, true, false, false, false, false, true]);
+        assert_eq!(byte_pixels(0), [false; 8]);
+    }
+
+    #[test]
+    fn test_state_checksum() {
+        let mut chip8 = Chip8::new();
+        let mut other = Chip8::new();
+        for machine in [&mut chip8, &mut other] {
+            machine.load_vec(vec![0x6A45, 0xA300, 0xD005]).unwrap();
+            for _ in 0..3 {
+                machine.tick(None);
+            }
+        }
+        assert_eq!(chip8.state_checksum(), other.state_checksum());
+        other.memory[0xF00] ^= 1;
+        assert_ne!(chip8.state_checksum(), other.state_checksum());
+        other.memory[0xF00] ^= 1;
+        other.reg_i += 1;
+        assert_ne!(chip8.state_checksum(), other.state_checksum());
+        other.reg_i -= 1;
+        other.display[0] = !other.display[0];
+        assert_ne!(chip8.state_checksum(), other.state_checksum());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6A45, 0x2208]).unwrap();
+        chip8.tick(None);
+        chip8.tick(None);
+        let snapshot = chip8.snapshot();
+        let mut other = Chip8::new();
+        other.restore(&snapshot);
+        assert_eq!(other.snapshot(), snapshot);
+        assert_eq!(other.stack.pop(), Ok(0x204));
+    }
+
+    #[test]
+    fn test_pc_history() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6A45, 0x1200]).unwrap();
+        for _ in 0..100 {
+            chip8.tick(None);
+        }
+        assert_eq!(chip8.pc_history.len(), PC_HISTORY_LEN);
+        assert_eq!(chip8.pc_history.back(), Some(&0x202));
+    }
+
+    #[test]
+    fn test_run_frame_deterministic() {
+        let run = || {
+            let mut chip8 = Chip8::new();
+            chip8.seed_rng(42);
+            chip8.load_vec(vec![0xC0FF, 0xC1FF, 0xF118, 0x1200]).unwrap();
+            let result = chip8.run_frame(&[false; 16], 10);
+            (result, chip8.reg)
+        };
+        let (result, reg) = run();
+        assert_eq!(run(), (result, reg));
+        assert_eq!(result.instructions, 10);
+        assert_eq!(result.sound, reg[1] > 1);
+        assert!(!result.redraw);
+
+        // Only the frame that draws reports a redraw
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xA000, 0xD011, 0x1204]).unwrap();
+        assert!(chip8.run_frame(&[false; 16], 3).redraw);
+        assert!(!chip8.run_frame(&[false; 16], 3).redraw);
+    }
+
+    #[test]
+    fn test_reseed_reproduces_cxnn() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF]).unwrap();
+        chip8.seed_rng(1234);
+        chip8.run_frame(&[false; 16], 4);
+        let first = chip8.reg;
+
+        // Resetting restarts the RNG from the same seed
+        chip8.reset();
+        chip8.load_vec(vec![0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF]).unwrap();
+        chip8.run_frame(&[false; 16], 4);
+        assert_eq!(chip8.reg, first);
+        assert_eq!(chip8.seed, 1234);
+
+        chip8.reset();
+        chip8.seed_rng(1235);
+        chip8.load_vec(vec![0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF]).unwrap();
+        chip8.run_frame(&[false; 16], 4);
+        assert_ne!(chip8.reg, first);
+    }
+
+    #[test]
+    fn test_reset_rng_policy() {
+        let program = vec![0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF, 0xC4FF, 0xC5FF, 0xC6FF, 0xC7FF];
+        let mut chip8 = Chip8::new();
+        chip8.seed_rng(99);
+        chip8.load_vec(program.clone()).unwrap();
+        chip8.run_frame(&[false; 16], 8);
+        let first = chip8.reg;
+
+        chip8.reset_rng = ResetRng::Reseed;
+        chip8.reset();
+        chip8.load_vec(program.clone()).unwrap();
+        chip8.run_frame(&[false; 16], 8);
+        assert_eq!(chip8.reg, first);
+
+        chip8.reset_rng = ResetRng::NewSeed;
+        chip8.reset();
+        assert_ne!(chip8.seed, 99);
+        chip8.load_vec(program).unwrap();
+        chip8.run_frame(&[false; 16], 8);
+        assert_ne!(chip8.reg, first);
+    }
+
+    #[test]
+    fn test_run_frame_random_memory_never_panics() {
+        let mut rng = StdRng::seed_from_u64(0xC8);
+        for seed in 0..200 {
+            let mut chip8 = Chip8::new();
+            chip8.seed_rng(seed);
+            rng.fill(&mut chip8.memory[0x200..]);
+            rng.fill(&mut chip8.reg);
+            chip8.reg_i = rng.gen();
+            for _ in 0..5 {
+                let keys: [bool; 16] = rng.gen();
+                chip8.run_frame(&keys, 100);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_bounded() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x7001, 0x1200]).unwrap(); // Loops forever
+        assert_eq!(chip8.run_bounded(500, None), RunOutcome::TimedOut { instructions: 500 });
+        assert_eq!(chip8.run_bounded(u64::MAX, Some(Duration::ZERO)), RunOutcome::TimedOut { instructions: 0 });
+
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x7001, 0x1202]).unwrap(); // Halts on the second instruction
+        assert_eq!(chip8.run_bounded(500, None), RunOutcome::Halted { instructions: 2 });
+    }
+
+    #[test]
+    fn test_stack_overflow_and_underflow_dont_panic() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x2200]).unwrap();
+        chip8.run_frame(&[false; 16], 40);
+        assert_eq!(chip8.stack.elements().len(), 32);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x00EE]).unwrap();
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_display_to_string() {
+        let mut chip8 = Chip8::new();
+        chip8.display[1] = true;
+        chip8.display[64 * 31 + 63] = true;
+        let text = chip8.display_to_string();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 32);
+        assert!(rows[0].starts_with(".#.."));
+        assert!(rows[31].ends_with("..#"));
+    }
+
     #[test]
     fn test_fetch() {
         let mut chip8 = Chip8::new();
@@ -484,10 +1639,106 @@ mod tests {
         assert_eq!(chip8.fetch().code, (4 << 8) | 5);
     }
 
+    #[test]
+    fn test_fetch_odd_pc() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x1203, 0x006A, 0x4500]).unwrap();
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x203);
+        chip8.tick(None);
+        assert_eq!(chip8.opcode.code, 0x6A45);
+        assert_eq!(chip8.reg[0xA], 0x45);
+        assert_eq!(chip8.pc, 0x205);
+    }
+
+    #[test]
+    fn test_fetch_top_of_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.pc = 0xFFF;
+        chip8.memory[0xFFF] = 0x12;
+        chip8.memory[0x000] = 0x34;
+        assert_eq!(chip8.fetch().code, 0x1234);
+    }
+
+    #[test]
+    fn test_pc_policy_overflow() {
+        for (policy, ran_at) in [(PcPolicy::Wrap, Some(0x000)), (PcPolicy::Clamp, Some(0xFFE)), (PcPolicy::Halt, None)] {
+            let mut chip8 = Chip8::new();
+            chip8.pc_policy = policy;
+            chip8.pc = 0x1000; // Just past the top of memory
+            chip8.tick(None);
+            assert_eq!(chip8.pc_history.back().copied(), ran_at, "{:?}", policy);
+            assert_eq!(chip8.halted, ran_at.is_none(), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_pc_policy_underflow() {
+        for (policy, ran_at) in [(PcPolicy::Wrap, Some(0x050)), (PcPolicy::Clamp, Some(0x200)), (PcPolicy::Halt, None)] {
+            let mut chip8 = Chip8::new();
+            chip8.pc_policy = policy;
+            chip8.pc = 0x050; // Inside the font
+            chip8.tick(None);
+            assert_eq!(chip8.pc_history.back().copied(), ran_at, "{:?}", policy);
+            assert_eq!(chip8.halted, ran_at.is_none(), "{:?}", policy);
+        }
+        let mut chip8 = Chip8::new();
+        chip8.pc_policy = PcPolicy::Halt;
+        chip8.pc = 0x050;
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x050); // Stays halted
+        chip8.reset();
+        assert!(!chip8.halted);
+    }
+
+    #[test]
+    fn test_next_opcode_doesnt_advance() {
+        let mut chip8 = Chip8::new();
+        chip8.pc = 0xFFF;
+        chip8.memory[0xFFF] = 0x6A;
+        chip8.memory[0x000] = 0x45;
+        let before = chip8.snapshot();
+        let opcode = chip8.next_opcode();
+        assert_eq!(opcode.code, 0x6A45); // The second byte wraps to the bottom of memory
+        assert_eq!((opcode.x, opcode.nn), (0xA, 0x45));
+        assert_eq!(chip8.snapshot(), before);
+        assert_eq!(chip8.opcode.code, 0x0000);
+    }
+
+    #[test]
+    fn test_op_unknown_logs_once() {
+        captured_warnings("");
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xF1FF, 0xF2FF, 0x1200]).unwrap();
+        for _ in 0..6 {
+            chip8.tick(None);
+        }
+        assert_eq!(captured_warnings("Unknown opcode F1FF").len(), 1);
+        assert_eq!(captured_warnings("Unknown opcode F2FF").len(), 1);
+        // A reset forgets what's been seen
+        chip8.reset();
+        chip8.load_vec(vec![0xF1FF]).unwrap();
+        chip8.tick(None);
+        assert_eq!(captured_warnings("Unknown opcode F1FF").len(), 2);
+    }
+
+    #[test]
+    fn test_op_unknown_verbose() {
+        captured_warnings("");
+        let mut chip8 = Chip8::new();
+        chip8.log_unknown_once = false;
+        chip8.load_vec(vec![0xF3FF, 0x1200]).unwrap();
+        for _ in 0..4 {
+            chip8.tick(None);
+        }
+        assert_eq!(captured_warnings("Unknown opcode F3FF").len(), 2);
+    }
+
     #[test]
     fn test_op_0nnn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x0208]);
+        chip8.load_vec(vec![0x0208]).unwrap();
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x208);
     }
@@ -495,8 +1746,8 @@ mod tests {
     #[test]
     fn test_op_00ee() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x00ee]);
-        chip8.stack.push(0x206);
+        chip8.load_vec(vec![0x00ee]).unwrap();
+        chip8.stack.push(0x206).unwrap();
         assert_eq!(chip8.pc, 0x200);
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x206);
@@ -505,26 +1756,43 @@ mod tests {
     #[test]
     fn test_op_1nnn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x1208]);
+        chip8.load_vec(vec![0x1208]).unwrap();
         assert_eq!(chip8.pc, 0x200);
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x208);
+        assert!(!chip8.spinning);
+    }
+
+    #[test]
+    fn test_op_1nnn_self_jump_sets_spinning() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6001, 0x1202]).unwrap();
+        chip8.tick(None);
+        chip8.tick(None);
+        assert!(chip8.spinning);
+        assert_eq!(chip8.pc, 0x202);
+
+        let mut chip8 = Chip8::new();
+        chip8.detect_idle_loop = false;
+        chip8.load_vec(vec![0x1200]).unwrap();
+        chip8.tick(None);
+        assert!(!chip8.spinning);
     }
 
     #[test]
     fn test_op_2nnn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x2208]);
+        chip8.load_vec(vec![0x2208]).unwrap();
         assert_eq!(chip8.pc, 0x200);
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x208);
-        assert_eq!(chip8.stack.pop(), 0x202)
+        assert_eq!(chip8.stack.pop(), Ok(0x202))
     }
 
     #[test]
     fn test_op_3xnn_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x3AFF]);
+        chip8.load_vec(vec![0x3AFF]).unwrap();
         chip8.reg[0xA] = 0xFF;
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x204);
@@ -533,7 +1801,7 @@ mod tests {
     #[test]
     fn test_op_3xnn_no_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x3AFF]);
+        chip8.load_vec(vec![0x3AFF]).unwrap();
         chip8.reg[0xA] = 0xF0;
         chip8.tick(None);
         assert_ne!(chip8.pc, 0x204);
@@ -542,7 +1810,7 @@ mod tests {
     #[test]
     fn test_op_4xnn_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x4AFF]);
+        chip8.load_vec(vec![0x4AFF]).unwrap();
         chip8.reg[0xA] = 0xF0;
         chip8.tick(None);
         assert_eq!(chip8.pc, 0x204);
@@ -551,7 +1819,7 @@ mod tests {
     #[test]
     fn test_op_4xnn_no_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x4AFF]);
+        chip8.load_vec(vec![0x4AFF]).unwrap();
         chip8.reg[0xA] = 0xFF;
         chip8.tick(None);
         assert_ne!(chip8.pc, 0x204);
@@ -560,7 +1828,7 @@ mod tests {
     #[test]
     fn test_op_5xy0_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x5AB0]);
+        chip8.load_vec(vec![0x5AB0]).unwrap();
         chip8.reg[0xA] = 0xF0;
         chip8.reg[0xB] = 0xF0;
         chip8.tick(None);
@@ -570,17 +1838,59 @@ mod tests {
     #[test]
     fn test_op_5xy0_no_skip() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x5AB0]);
+        chip8.load_vec(vec![0x5AB0]).unwrap();
         chip8.reg[0xA] = 0x0F;
         chip8.reg[0xB] = 0xF0;
         chip8.tick(None);
         assert_ne!(chip8.pc, 0x204);
     }
 
+    #[test]
+    fn test_op_9xy0_skip() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x9AB0]).unwrap();
+        chip8.reg[0xA] = 0x0F;
+        chip8.reg[0xB] = 0xF0;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x204);
+    }
+
+    #[test]
+    fn test_op_9xy0_no_skip() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x9AB0]).unwrap();
+        chip8.reg[0xA] = 0xF0;
+        chip8.reg[0xB] = 0xF0;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_op_bnnn() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xB300]).unwrap();
+        chip8.reg[0x0] = 0x12;
+        chip8.reg[0x3] = 0x34;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x312);
+    }
+
+    #[test]
+    fn test_op_bnnn_jump_vx() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.jump_vx = true;
+        chip8.load_vec(vec![0xB300]).unwrap();
+        chip8.reg[0x0] = 0x12;
+        chip8.reg[0x3] = 0x34;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x334);
+        assert!(chip8.reg_read.contains(&0x3));
+    }
+
     #[test]
     fn test_op_6xnn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x6A45]);
+        chip8.load_vec(vec![0x6A45]).unwrap();
         chip8.tick(None);
         assert_eq!(chip8.reg[0xA], 0x45);
     }
@@ -588,7 +1898,7 @@ mod tests {
     #[test]
     fn test_op_7xnn_wrap() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x7A02]);
+        chip8.load_vec(vec![0x7A02]).unwrap();
         chip8.reg[0xA] = 0xFF;
         chip8.tick(None);
         assert_eq!(chip8.reg[0xA], 0x01);
@@ -598,7 +1908,7 @@ mod tests {
     #[test]
     fn test_op_7xnn_no_wrap() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x7A10]);
+        chip8.load_vec(vec![0x7A10]).unwrap();
         chip8.reg[0xA] = 0x0F;
         chip8.tick(None);
         assert_eq!(chip8.reg[0xA], 0x0F + 0x10);
@@ -607,7 +1917,7 @@ mod tests {
     #[test]
     fn test_op_8xy0() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB0]);
+        chip8.load_vec(vec![0x8AB0]).unwrap();
         chip8.reg[0xA] = 7;
         chip8.reg[0xB] = 10;
         chip8.tick(None);
@@ -617,7 +1927,7 @@ mod tests {
     #[test]
     fn test_op_8xy2() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB2]);
+        chip8.load_vec(vec![0x8AB2]).unwrap();
         chip8.reg[0xA] = 0b11111100;
         chip8.reg[0xB] = 0b00111111;
         chip8.tick(None);
@@ -625,10 +1935,104 @@ mod tests {
         assert_eq!(chip8.reg[0xB], 0b00111111);
     }
 
+    #[test]
+    fn test_op_8xy1() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x8AB1]).unwrap();
+        chip8.reg[0xA] = 0b11000000;
+        chip8.reg[0xB] = 0b00000011;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0b11000011);
+        assert_eq!(chip8.reg[0xB], 0b00000011);
+        assert!(chip8.reg_write.contains(&0xA));
+        assert!(chip8.reg_read.contains(&0xB));
+    }
+
+    #[test]
+    fn test_op_8xy3() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x8AB3]).unwrap();
+        chip8.reg[0xA] = 0b11110000;
+        chip8.reg[0xB] = 0b00111100;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0b11001100);
+        assert_eq!(chip8.reg[0xB], 0b00111100);
+        assert!(chip8.reg_write.contains(&0xA));
+        assert!(chip8.reg_read.contains(&0xB));
+    }
+
+    #[test]
+    fn test_op_8xy6() {
+        // (shift_vx, expected VA, expected VF)
+        for (shift_vx, expected, expected_vf) in [(false, 0b0000_0011, 1), (true, 0b0100_0000, 0)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.shift_vx = shift_vx;
+            chip8.load_vec(vec![0x8AB6]).unwrap();
+            chip8.reg[0xA] = 0b1000_0000;
+            chip8.reg[0xB] = 0b0000_0111;
+            chip8.tick(None);
+            assert_eq!(chip8.reg[0xA], expected, "shift_vx {}", shift_vx);
+            assert_eq!(chip8.reg[0xF], expected_vf, "shift_vx {}", shift_vx);
+            assert_eq!(chip8.reg[0xB], 0b0000_0111);
+        }
+    }
+
+    #[test]
+    fn test_op_8xy6_into_vf() {
+        // The shifted out bit overwrites the result when X is F
+        for shift_vx in [false, true] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.shift_vx = shift_vx;
+            chip8.load_vec(vec![0x8FF6]).unwrap();
+            chip8.reg[0xF] = 0b0000_0011;
+            chip8.tick(None);
+            assert_eq!(chip8.reg[0xF], 1, "shift_vx {}", shift_vx);
+        }
+    }
+
+    #[test]
+    fn test_op_8xye() {
+        // (shift_vx, expected VA, expected VF)
+        for (shift_vx, expected, expected_vf) in [(false, 0b1111_1110, 0), (true, 0b0000_0010, 1)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.shift_vx = shift_vx;
+            chip8.load_vec(vec![0x8ABE]).unwrap();
+            chip8.reg[0xA] = 0b1000_0001;
+            chip8.reg[0xB] = 0b0111_1111;
+            chip8.tick(None);
+            assert_eq!(chip8.reg[0xA], expected, "shift_vx {}", shift_vx);
+            assert_eq!(chip8.reg[0xF], expected_vf, "shift_vx {}", shift_vx);
+            assert_eq!(chip8.reg[0xB], 0b0111_1111);
+        }
+
+        let mut chip8 = Chip8::new();
+        chip8.quirks.shift_vx = true;
+        chip8.load_vec(vec![0x8FFE]).unwrap();
+        chip8.reg[0xF] = 0b1100_0000;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xF], 1);
+    }
+
+    #[test]
+    fn test_op_8xy1_vf_per_profile() {
+        for (profile, expected_vf) in [
+            (quirks::Profile::CosmacVip, 0),
+            (quirks::Profile::Chip48, 0x77),
+            (quirks::Profile::SuperChip, 0x77),
+        ] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks = Quirks::from_profile(profile);
+            chip8.load_vec(vec![0x8AB1]).unwrap();
+            chip8.reg[0xF] = 0x77;
+            chip8.tick(None);
+            assert_eq!(chip8.reg[0xF], expected_vf, "{}", profile.name());
+        }
+    }
+
     #[test]
     fn test_op_8xy4_carry() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB4]);
+        chip8.load_vec(vec![0x8AB4]).unwrap();
         chip8.reg[0xA] = 255;
         chip8.reg[0xB] = 7;
         chip8.tick(None);
@@ -640,31 +2044,33 @@ mod tests {
     #[test]
     fn test_op_8xy4_no_carry() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB4]);
+        chip8.load_vec(vec![0x8AB4]).unwrap();
         chip8.reg[0xA] = 2;
         chip8.reg[0xB] = 5;
         chip8.tick(None);
         assert_eq!(chip8.reg[0xA], 7);
         assert_eq!(chip8.reg[0xB], 5);
-        assert_eq!(chip8.reg[0xF], 0)
+        assert_eq!(chip8.reg[0xF], 0);
+        assert_eq!(chip8.reg_write, vec![0xA, 0xF]);
     }
 
     #[test]
     fn test_op_8xy5_borrow() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB5]);
+        chip8.load_vec(vec![0x8AB5]).unwrap();
         chip8.reg[0xA] = 0;
         chip8.reg[0xB] = 7;
         chip8.tick(None);
         assert_eq!(chip8.reg[0xA], 249);
         assert_eq!(chip8.reg[0xB], 7);
-        assert_eq!(chip8.reg[0xF], 0)
+        assert_eq!(chip8.reg[0xF], 0);
+        assert_eq!(chip8.reg_write, vec![0xA, 0xF]);
     }
 
     #[test]
     fn test_op_8xy5_no_borrow() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0x8AB5]);
+        chip8.load_vec(vec![0x8AB5]).unwrap();
         chip8.reg[0xA] = 7;
         chip8.reg[0xB] = 5;
         chip8.tick(None);
@@ -673,10 +2079,79 @@ mod tests {
         assert_eq!(chip8.reg[0xF], 1)
     }
 
+    #[test]
+    fn test_op_8xy7() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x8AB7, 0x8AB7]).unwrap();
+        chip8.reg[0xA] = 5;
+        chip8.reg[0xB] = 7;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 2);
+        assert_eq!(chip8.reg[0xF], 1);
+        chip8.reg[0xA] = 9;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 254);
+        assert_eq!(chip8.reg[0xF], 0);
+        assert_eq!(chip8.reg_write, vec![0xA, 0xF]);
+    }
+
+    #[test]
+    fn test_op_8xy7_into_vf() {
+        // The borrow flag overwrites the result when X is F
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x8FB7]).unwrap();
+        chip8.reg[0xF] = 5;
+        chip8.reg[0xB] = 7;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xF], 1);
+        assert_eq!(chip8.reg[0xB], 7);
+    }
+
+    // Keeps warnings so tests can check what was logged
+    struct CapturingLogger;
+
+    static CAPTURED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                CAPTURED.lock().unwrap().push(record.args().to_string());
+            }
+        }
+        fn flush(&self) {}
+    }
+
+    fn captured_warnings(containing: &str) -> Vec<String> {
+        static LOGGER: CapturingLogger = CapturingLogger;
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Warn);
+        }
+        CAPTURED.lock().unwrap().iter().filter(|message| message.contains(containing)).cloned().collect()
+    }
+
+    #[test]
+    fn test_warn_overflow() {
+        captured_warnings("");
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x73F0, 0x7305, 0x7405]).unwrap();
+        chip8.reg[3] = 0x20;
+        chip8.tick(None);
+        chip8.warn_overflow = true;
+        chip8.reg[3] = 0xFE;
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(captured_warnings("wrapped: V3"), vec!["7305 at 0x202 wrapped: V3 (0xfe) + 0x05 = 0x03"]);
+        assert!(captured_warnings("wrapped: V4").is_empty());
+        assert!(captured_warnings("73F0").is_empty());
+    }
+
     #[test]
     fn test_op_annn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xa123]);
+        chip8.load_vec(vec![0xa123]).unwrap();
         chip8.tick(None);
         assert_eq!(chip8.reg_i, 0x123);
     }
@@ -684,7 +2159,7 @@ mod tests {
     #[test]
     fn test_op_dxyn() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xd003, 0xd003]);
+        chip8.load_vec(vec![0xd003, 0xd003]).unwrap();
         chip8.reg_i = 0;
         chip8.tick(None);
         assert!(chip8.display[0]); // Drew white at 0x0
@@ -694,10 +2169,238 @@ mod tests {
         assert_eq!(chip8.reg[0xF], 1); // Bit flipped, VF set
     }
 
+    #[test]
+    fn test_op_dxyn_clips_at_edges() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xD012]).unwrap();
+        chip8.reg_i = 0; // Top of the "0" glyph, 0xF0 0x90
+        chip8.reg[0] = 62;
+        chip8.reg[1] = 31;
+        chip8.tick(None);
+        assert!(chip8.display[31 * 64 + 62]);
+        assert!(chip8.display[31 * 64 + 63]);
+        assert_eq!(chip8.display.iter().filter(|pixel| **pixel).count(), 2);
+    }
+
+    #[test]
+    fn test_op_dxyn_wraps_horizontally_only() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.wrap_left = true;
+        chip8.quirks.wrap_right = true;
+        chip8.load_vec(vec![0xD012]).unwrap();
+        chip8.reg_i = 0;
+        chip8.reg[0] = 62;
+        chip8.reg[1] = 31;
+        chip8.tick(None);
+        assert!(chip8.display[31 * 64]); // Wrapped round to the left
+        assert!(chip8.display[31 * 64 + 1]);
+        assert!(!chip8.display[62]); // The second row is still clipped at the bottom
+        assert_eq!(chip8.display.iter().filter(|pixel| **pixel).count(), 4);
+    }
+
+    #[test]
+    fn test_op_dxyn_one_sided_wrap_clips() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.wrap_bottom = true;
+        chip8.load_vec(vec![0xD012]).unwrap();
+        chip8.reg_i = 0;
+        chip8.reg[0] = 0;
+        chip8.reg[1] = 31;
+        chip8.tick(None);
+        assert!(!chip8.display[0]); // The top edge clips, so the second row doesn't reappear
+        assert_eq!(chip8.display.iter().filter(|pixel| **pixel).count(), 4);
+    }
+
+    #[test]
+    fn test_op_dxyn_counts_clipped_rows_in_hires() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::from_profile(quirks::Profile::SuperChip);
+        chip8.load_vec(vec![0x00FF, 0xD015, 0x00FE, 0xD015]).unwrap();
+        chip8.reg_i = 0;
+        chip8.reg[1] = 62;
+        chip8.tick(None);
+        assert_eq!(chip8.display_dimensions(), (128, 64));
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xF], 3); // Rows 64, 65 and 66 fell off the bottom
+        assert!(chip8.display[62 * 128]);
+
+        // Low-res only flags collisions
+        chip8.tick(None);
+        assert_eq!(chip8.display_dimensions(), (64, 32));
+        chip8.reg[1] = 30;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xF], 0);
+
+        // Without the quirk high-res behaves the same
+        chip8.quirks.vf_clipped_rows = false;
+        chip8.pc = 0x200;
+        chip8.tick(None);
+        chip8.reg[1] = 62;
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xF], 0);
+    }
+
+    #[test]
+    fn test_op_00fe_00ff() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x00FF, 0x00FE]).unwrap();
+        chip8.display[0] = true;
+        chip8.tick(None);
+        assert!(chip8.hires);
+        assert_eq!(chip8.frame().pixels().len(), 128 * 64);
+        assert!(!chip8.display[0]); // Switching mode clears the display
+        chip8.display[127] = true;
+        chip8.tick(None);
+        assert!(!chip8.hires);
+        assert_eq!(chip8.display_to_string().lines().count(), 32);
+        assert!(chip8.display.iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn test_pending_sprite() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xD012, 0x6000]).unwrap();
+        chip8.reg_i = 0; // Top of the "0" glyph, 0xF0 0x90
+        chip8.reg[0] = 62;
+        chip8.reg[1] = 30;
+        let mut pending = chip8.pending_sprite();
+        pending.sort();
+        assert_eq!(pending, vec![30 * 64 + 62, 30 * 64 + 63, 31 * 64 + 62]);
+        chip8.tick(None);
+        assert!(chip8.pending_sprite().is_empty());
+        let drawn: Vec<usize> = (0..chip8.display.len()).filter(|i| chip8.display[*i]).collect();
+        assert_eq!(drawn, pending);
+    }
+
+    #[test]
+    fn test_op_dxyn_start_position_wraps() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xD011]).unwrap();
+        chip8.reg_i = 0;
+        chip8.reg[0] = 64 + 3;
+        chip8.reg[1] = 32 + 2;
+        chip8.tick(None);
+        assert!(chip8.display[2 * 64 + 3]);
+    }
+
+    #[test]
+    fn test_op_ex9e_press_key() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xEA9E, 0x0000, 0xEA9E]).unwrap();
+        chip8.reg[0xA] = 0x7;
+        chip8.press_key(0x7);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x204);
+        chip8.release_key(0x7);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x206);
+    }
+
+    #[test]
+    fn test_op_ex9e_keypress() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xEA9E, 0x0000, 0xEA9E, 0x0000, 0xEA9E]).unwrap();
+        chip8.reg[0xA] = 0x7;
+        chip8.tick(Some(0x7));
+        assert_eq!(chip8.pc, 0x204);
+        chip8.tick(Some(0x3));
+        assert_eq!(chip8.pc, 0x206);
+        // No key at all never skips
+        chip8.pc = 0x208;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x20A);
+        assert!(chip8.reg_read.contains(&0xA));
+    }
+
+    #[test]
+    fn test_op_exa1() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xEAA1, 0x0000, 0xEAA1]).unwrap();
+        chip8.reg[0xA] = 0x7;
+        chip8.tick(Some(0x3));
+        assert_eq!(chip8.pc, 0x204);
+        chip8.tick(Some(0x7));
+        assert_eq!(chip8.pc, 0x206);
+    }
+
+    #[test]
+    fn test_key_skips_with_several_keys_down() {
+        let mut chip8 = Chip8::new();
+        chip8.reg[0xA] = 0x7;
+        chip8.reg[0xB] = 0x5;
+        for key in [0x2, 0x7, 0xC] {
+            chip8.press_key(key);
+        }
+        // 7 is down and 5 isn't, whatever else is held
+        for (opcode, skips) in [(0xEA9E, true), (0xEAA1, false), (0xEB9E, false), (0xEBA1, true)] {
+            chip8.load_vec(vec![opcode]).unwrap();
+            chip8.pc = 0x200;
+            chip8.tick(None);
+            assert_eq!(chip8.pc, if skips { 0x204 } else { 0x202 }, "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_single_key_quirk() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.single_key = true;
+        chip8.load_vec(vec![0xEA9E, 0x0000, 0xEB9E]).unwrap();
+        chip8.reg[0xA] = 0x7;
+        chip8.reg[0xB] = 0x2;
+        chip8.press_key(0x7);
+        chip8.press_key(0x2);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x202); // Only 2, the lowest held key, is seen
+        chip8.pc = 0x204;
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x208);
+    }
+
+    #[test]
+    fn test_op_fx0a_blocks() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xF30A]).unwrap();
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x200); // Still waiting
+        chip8.press_key(0xB);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.reg[3], 0xB);
+    }
+
+    #[test]
+    fn test_op_fx0a_polling_quirk() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.fx0a_polling = true;
+        chip8.load_vec(vec![0xF30A, 0xF40A, 0xF50A]).unwrap();
+        chip8.reg[3] = 0x7;
+        // Nothing pressed yet, so it carries on and leaves V3 alone
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.reg[3], 0x7);
+        chip8.press_key(0x2);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[4], 0x2);
+        // Released, it still gets the last key
+        chip8.release_key(0x2);
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x206);
+        assert_eq!(chip8.reg[5], 0x2);
+    }
+
+    #[test]
+    fn test_press_invalid_key() {
+        let mut chip8 = Chip8::new();
+        chip8.press_key(0x10);
+        assert_eq!(chip8.keys, [false; 16]);
+        assert!(!chip8.is_key_down(0x10));
+    }
+
     #[test]
     fn test_op_fx07() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xF207]);
+        chip8.load_vec(vec![0xF207]).unwrap();
         chip8.tick(None);
         assert_eq!(chip8.reg[2], chip8.delay_timer);
     }
@@ -705,7 +2408,7 @@ mod tests {
     #[test]
     fn test_op_fx15() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xFA15]);
+        chip8.load_vec(vec![0xFA15]).unwrap();
         chip8.reg[0xA] = 57;
         chip8.tick(None);
         assert_eq!(chip8.delay_timer, 57);
@@ -714,16 +2417,428 @@ mod tests {
     #[test]
     fn test_op_fx18() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xFB18]);
+        chip8.load_vec(vec![0xFB18]).unwrap();
         chip8.reg[0xB] = 53;
         chip8.tick(None);
         assert_eq!(chip8.sound_timer, 53);
     }
 
+    #[test]
+    fn test_op_fx18_sound_floor() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.sound_floor = true;
+        chip8.load_vec(vec![0xF018, 0xF118]).unwrap();
+        chip8.reg[0] = 1;
+        chip8.reg[1] = 2;
+        chip8.tick(None);
+        assert_eq!(chip8.sound_timer, 0);
+        chip8.tick(None);
+        assert_eq!(chip8.sound_timer, 2);
+
+        // Values below 2 still stop a running tone
+        for value in [1, 0] {
+            chip8.reset();
+            chip8.load_vec(vec![0xF018]).unwrap();
+            chip8.sound_timer = 30;
+            chip8.reg[0] = value;
+            chip8.tick(None);
+            assert_eq!(chip8.sound_timer, 0, "FX18 with {}", value);
+        }
+    }
+
+    #[test]
+    fn test_tick_timers() {
+        let mut chip8 = Chip8::new();
+        chip8.delay_timer = 10;
+        chip8.sound_timer = 10;
+        chip8.tick_timers();
+        assert_eq!(chip8.delay_timer, 9);
+        assert_eq!(chip8.sound_timer, 9);
+    }
+
+    #[test]
+    fn test_sound_timer_slowdown() {
+        let mut chip8 = Chip8::new();
+        chip8.sound_timer_slowdown = 4;
+        chip8.delay_timer = 10;
+        chip8.sound_timer = 10;
+        for _ in 0..8 {
+            chip8.tick_timers();
+        }
+        assert_eq!(chip8.delay_timer, 2);
+        assert_eq!(chip8.sound_timer, 8);
+    }
+
+    #[test]
+    fn test_op_00e0() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x00E0]).unwrap();
+        chip8.display.fill(true);
+        chip8.tick(None);
+        assert!(chip8.display.iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn test_clear_display_leaves_registers() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6A45, 0xA123]).unwrap();
+        chip8.tick(None);
+        chip8.tick(None);
+        chip8.display.fill(true);
+        let before = chip8.snapshot();
+        chip8.clear_display();
+        assert!(chip8.display.iter().all(|pixel| !pixel));
+        assert_eq!(chip8.reg, before.reg);
+        assert_eq!(chip8.reg_i, before.reg_i);
+        assert_eq!(chip8.pc, before.pc);
+        assert_eq!(chip8.display_dimensions(), (64, 32));
+    }
+
+    #[test]
+    fn test_bank_switching() {
+        let mut chip8 = Chip8::new();
+        chip8.bank_switching = true;
+        let mut rom = vec![0x11; BANK_SIZE];
+        rom.extend([0x22, 0x33]);
+        chip8.load_bytes(&rom).unwrap();
+        assert_eq!(chip8.bank_count(), 2);
+        assert_eq!(chip8.memory[0x200], 0x11);
+        assert_eq!(chip8.memory[BANK_REGISTER], 0);
+        // FX33 with I at the register writes 0, 0, then 1 there, ending in bank 1
+        chip8.reg[0] = 1;
+        chip8.reg_i = (BANK_REGISTER - 2) as u16;
+        chip8.load_vec(vec![0xF033]).unwrap();
+        chip8.tick(None);
+        assert_eq!(chip8.bank, 1);
+        assert_eq!(chip8.memory[0x200..0x203], [0x22, 0x33, 0x00]);
+        assert_eq!(chip8.read_mem(BANK_REGISTER), 1);
+        chip8.select_bank(2);
+        assert_eq!(chip8.bank, 1);
+        chip8.select_bank(0);
+        assert_eq!(chip8.memory[0x200..0x203], [0x11; 3]);
+        // More banks than the register can count are rejected, keeping the loaded ROM
+        let rom = vec![0x44; BANK_SIZE * MAX_BANKS + 1];
+        assert!(matches!(chip8.load_bytes(&rom), Err(Chip8Error::RomTooLarge { .. })));
+        assert_eq!(chip8.bank_count(), 2);
+        assert!(chip8.load_bytes(&rom[1..]).is_ok());
+        assert_eq!(chip8.bank_count(), MAX_BANKS);
+    }
+
+    #[test]
+    fn test_disabled_opcodes() {
+        assert!(opcodes::OPCODES.len() <= 64);
+        let mut chip8 = Chip8::new();
+        chip8.set_opcode_enabled("7XNN", false).unwrap();
+        assert!(chip8.set_opcode_enabled("ZZZZ", false).is_err());
+        chip8.load_vec(vec![0x6005, 0x7003, 0x7103, 0x6101]).unwrap();
+        for _ in 0..4 {
+            chip8.tick(None);
+        }
+        assert_eq!(chip8.reg[0], 5);
+        assert_eq!(chip8.reg[1], 1);
+        assert!(!chip8.halted);
+
+        chip8.reset();
+        chip8.disabled_opcode = DisabledOpcode::Error;
+        chip8.load_vec(vec![0x6005, 0x7003, 0x6101]).unwrap();
+        for _ in 0..3 {
+            chip8.tick(None);
+        }
+        assert!(chip8.halted);
+        assert_eq!(chip8.pc, 0x204);
+        assert_eq!(chip8.reg[1], 0);
+        chip8.set_opcode_enabled("7XNN", true).unwrap();
+        assert_eq!(chip8.enabled_opcodes, u64::MAX);
+    }
+
+    #[test]
+    fn test_custom_opcode() {
+        let mut chip8 = Chip8::new();
+        // 01NN adds NN to VF, and E0FF (otherwise unknown) sets I
+        chip8.register_custom_opcode(0x0100, 0xFF00, |chip8| chip8.reg[0xF] += chip8.opcode.nn);
+        chip8.register_custom_opcode(0xE0FF, 0xFFFF, |chip8| chip8.reg_i = 0x123);
+        chip8.load_vec(vec![0x0105, 0x0102, 0xE0FF, 0x0300]).unwrap();
+        for _ in 0..3 {
+            chip8.tick(None);
+        }
+        assert_eq!(chip8.reg[0xF], 7);
+        assert_eq!(chip8.reg_i, 0x123);
+        assert_eq!(chip8.pc, 0x206);
+        // Anything that doesn't match is still a machine code call
+        chip8.tick(None);
+        assert_eq!(chip8.pc, 0x300);
+    }
+
+    #[test]
+    fn test_load_bytes() {
+        let mut chip8 = Chip8::new();
+        chip8.load_bytes(&[0x6A, 0x45]).unwrap();
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0x45);
+        assert_eq!(chip8.rom, vec![0x6A, 0x45]);
+
+        // A ROM that doesn't fit leaves the last one loaded
+        assert!(matches!(chip8.load_bytes(&[0xFF; 4000]), Err(Chip8Error::RomTooLarge { size: 4000, space: 0xE00 })));
+        assert_eq!(chip8.rom, vec![0x6A, 0x45]);
+        assert_eq!(chip8.memory[0x202], 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut chip8 = Chip8::new();
+        assert!(matches!(chip8.load_rom("no_such_rom.ch8"), Err(Chip8Error::Io(_))));
+
+        chip8.load_vec(vec![0x00EE]).unwrap();
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::StackUnderflow)));
+
+        chip8.reset();
+        chip8.load_vec(vec![0x2200]).unwrap();
+        for _ in 0..stack::CAPACITY {
+            chip8.try_tick(None).unwrap();
+        }
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::StackOverflow)));
+
+        chip8.reset();
+        chip8.load_vec(vec![0xE000, 0x6001]).unwrap();
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::UnknownOpcode(0xE000))));
+        assert!(chip8.try_tick(None).is_ok());
+
+        chip8.reset();
+        chip8.quirks.wide_index = true;
+        chip8.reg_i = 0x2000;
+        chip8.load_vec(vec![0xF055]).unwrap();
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::AddressOutOfRange(0x2000))));
+        assert!(matches!(chip8.patch(0x000, &[1]), Err(Chip8Error::AddressOutOfRange(0))));
+        chip8.reset();
+        assert!(matches!(chip8.load_vec(vec![0x1200; 0x701]), Err(Chip8Error::RomTooLarge { size: 0xE02, space: 0xE00 })));
+        assert!(chip8.load_vec(vec![0x1200; 0x700]).is_ok());
+    }
+
+    #[test]
+    fn test_memory_protection() {
+        let mut chip8 = Chip8::new();
+        // I = 0x100, then store V0 there
+        chip8.load_vec(vec![0x6042, 0xA100, 0xF055]).unwrap();
+        chip8.protect_low_memory = true;
+        chip8.try_tick(None).unwrap();
+        chip8.try_tick(None).unwrap();
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::ProtectedWrite { pc: 0x204, address: 0x100 })));
+        assert!(chip8.halted);
+        assert_eq!(chip8.memory[0x100], 0);
+
+        // Writes after the faulting one don't reach the program
+        chip8.reset();
+        chip8.load_vec(vec![0x6042, 0xA1FF, 0xF255]).unwrap();
+        chip8.protect_low_memory = true;
+        chip8.try_tick(None).unwrap();
+        chip8.try_tick(None).unwrap();
+        assert!(matches!(chip8.try_tick(None), Err(Chip8Error::ProtectedWrite { pc: 0x204, address: 0x1FF })));
+        assert_eq!(chip8.memory[0x200..0x202], [0x60, 0x42]);
+
+        chip8.reset();
+        chip8.load_vec(vec![0x6042, 0xA100, 0xF055]).unwrap();
+        chip8.protect_low_memory = false;
+        for _ in 0..3 {
+            chip8.try_tick(None).unwrap();
+        }
+        assert_eq!(chip8.memory[0x100], 0x42);
+    }
+
+    #[test]
+    fn test_dirty_rect() {
+        let mut chip8 = Chip8::new();
+        // Two 1-row sprites, at (10, 5) and (20, 7)
+        chip8.load_vec(vec![0x600A, 0x6105, 0x6214, 0x6307, 0xA000, 0xD011, 0xD231]).unwrap();
+        let result = chip8.run_frame(&[false; 16], 7);
+        // Font 0's top row is 0xF0, four pixels wide
+        assert_eq!(result.dirty, Some(DirtyRect { x: 10, y: 5, width: 14, height: 3 }));
+        assert_eq!(chip8.frame().dirty, None);
+
+        let result = chip8.run_frame(&[false; 16], 0);
+        assert_eq!(result.dirty, None);
+        chip8.clear_display();
+        assert_eq!(chip8.frame().dirty, Some(DirtyRect { x: 0, y: 0, width: 64, height: 32 }));
+        assert!(chip8.take_dirty().is_some());
+        assert_eq!(chip8.take_dirty(), None);
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut chip8 = Chip8::new();
+        chip8.display[64 + 3] = true;
+        let frame = chip8.frame();
+        assert_eq!((frame.width, frame.height), (64, 32));
+        assert_eq!(frame.pixels().len(), 64 * 32);
+        assert!(frame.pixel(3, 1));
+        assert!(!frame.pixel(1, 3));
+    }
+
+    #[test]
+    fn test_op_00cn() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x00C3]).unwrap();
+        chip8.display[2 * 64 + 5] = true;
+        chip8.display[31 * 64] = true;
+        chip8.tick(None);
+        assert!(chip8.display[5 * 64 + 5]);
+        assert_eq!(chip8.display.iter().filter(|pixel| **pixel).count(), 1);
+    }
+
+    #[test]
+    fn test_op_00fb_00fc() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x00FB, 0x00FC, 0x00FC]).unwrap();
+        chip8.display[10] = true;
+        chip8.tick(None);
+        assert!(chip8.display[14]);
+        chip8.tick(None);
+        assert!(chip8.display[10]);
+        chip8.tick(None);
+        assert!(chip8.display[6]);
+        assert_eq!(chip8.display.iter().filter(|pixel| **pixel).count(), 1);
+    }
+
+    #[test]
+    fn test_half_pixel_scroll_quirk() {
+        for (half, down, right) in [(false, 4, 4), (true, 2, 2)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.half_scroll_lowres = half;
+            chip8.load_vec(vec![0x00C4, 0x00FB]).unwrap();
+            chip8.display[0] = true;
+            chip8.tick(None);
+            chip8.tick(None);
+            assert!(chip8.display[down * 64 + right], "half scroll {}", half);
+        }
+    }
+
+    #[test]
+    fn test_horizontal_scroll_distance() {
+        // (hires, half_scroll_lowres, horizontal_scroll, expected shift)
+        for (hires, half, distance, shift) in [
+            (false, false, 4, 4),
+            (false, true, 4, 2),
+            (true, false, 4, 4),
+            (true, true, 4, 4), // Halving only applies in low-res
+            (false, false, 8, 8),
+            (true, true, 2, 2),
+        ] {
+            let mut chip8 = Chip8::new();
+            chip8.set_hires(hires);
+            chip8.quirks.half_scroll_lowres = half;
+            chip8.quirks.horizontal_scroll = distance;
+            chip8.load_vec(vec![0x00FB, 0x00FC]).unwrap();
+            chip8.display[10] = true;
+            chip8.tick(None);
+            assert!(chip8.display[10 + shift], "{:?}", (hires, half, distance));
+            chip8.tick(None);
+            assert!(chip8.display[10]);
+        }
+    }
+
+    #[test]
+    fn test_op_cxnn() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xCA0F, 0xCB00]).unwrap();
+        chip8.reg[0xB] = 0xFF;
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA] & 0xF0, 0);
+        assert_eq!(chip8.reg[0xB], 0);
+    }
+
+    #[test]
+    fn test_op_cxnn_frozen() {
+        let mut chip8 = Chip8::new();
+        chip8.frozen_random = Some(0xA5);
+        chip8.load_vec(vec![0xCA0F, 0xCAFF, 0xCAF0]).unwrap();
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0x05);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0xA5);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0xA], 0xA0);
+    }
+
+    #[test]
+    fn test_op_fx1e() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xFA1E]).unwrap();
+        chip8.reg_i = 0x100;
+        chip8.reg[0xA] = 5;
+        chip8.tick(None);
+        assert_eq!(chip8.reg_i, 0x105);
+    }
+
+    #[test]
+    fn test_op_fx1e_overflow_classic() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xFA1E, 0xF065]).unwrap();
+        chip8.reg_i = 0xFFF;
+        chip8.reg[0xA] = 2;
+        chip8.tick(None);
+        assert_eq!(chip8.reg_i, 0x1001);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0], FONT[1]); // Wrapped round to 0x001
+    }
+
+    #[test]
+    fn test_op_fx1e_overflow_wide() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::from_profile(quirks::Profile::XoChip);
+        chip8.load_vec(vec![0xFA1E, 0xF065]).unwrap();
+        chip8.reg_i = 0xFFF;
+        chip8.reg[0xA] = 2;
+        chip8.reg[0] = 0xAA;
+        chip8.tick(None);
+        assert_eq!(chip8.reg_i, 0x1001);
+        chip8.tick(None);
+        assert_eq!(chip8.reg[0], 0); // Past the end of memory
+    }
+
+    #[test]
+    fn test_op_fx1e_overflow_vf() {
+        for (index_overflow_vf, start, expected_vf) in [(false, 0xFFF, 7), (true, 0xFFF, 1), (true, 0xFF0, 0)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.index_overflow_vf = index_overflow_vf;
+            chip8.load_vec(vec![0xFA1E]).unwrap();
+            chip8.reg_i = start;
+            chip8.reg[0xA] = 2;
+            chip8.reg[0xF] = 7;
+            chip8.tick(None);
+            assert_eq!(chip8.reg_i, start + 2);
+            assert_eq!(chip8.reg[0xF], expected_vf, "{} {:#05x}", index_overflow_vf, start);
+        }
+
+        // Starting past 0xFFF only counts when the add crosses the next boundary, including a 16-bit wrap
+        for (start, expected_i, expected_vf) in [(0x1000, 0x1002, 0), (0x1FFF, 0x2001, 1), (0xFFFF, 0x0001, 1)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.wide_index = true;
+            chip8.quirks.index_overflow_vf = true;
+            chip8.load_vec(vec![0xFA1E]).unwrap();
+            chip8.reg_i = start;
+            chip8.reg[0xA] = 2;
+            chip8.tick(None);
+            assert_eq!(chip8.reg_i, expected_i);
+            assert_eq!(chip8.reg[0xF], expected_vf, "{:#06x}", start);
+        }
+    }
+
+    #[test]
+    fn test_op_fx33_wraps_at_top_of_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xFA33]).unwrap();
+        chip8.reg_i = 0xFFF;
+        chip8.reg[0xA] = 123;
+        chip8.tick(None);
+        assert_eq!(chip8.memory[0xFFF], 1);
+        assert_eq!(chip8.memory[0x000], 2);
+        assert_eq!(chip8.memory[0x001], 3);
+    }
+
     #[test]
     fn test_op_fx29() {
         let mut chip8 = Chip8::new();
-        chip8.load_vec(vec![0xFA29]);
+        chip8.load_vec(vec![0xFA29]).unwrap();
         chip8.reg[0xA] = 0xE;
         chip8.tick(None);
         assert_eq!(chip8.reg_i, 70); // 0xE * 5
@@ -733,10 +2848,58 @@ mod tests {
     fn test_op_fx65() {
         let mut chip8 = Chip8::new();
         chip8.memory.fill(0xAA);
-        chip8.load_vec(vec![0xF265]);
+        chip8.load_vec(vec![0xF265]).unwrap();
         chip8.tick(None);
         assert_eq!(chip8.reg[0], 0xAA);
         assert_eq!(chip8.reg[1], 0xAA);
         assert_eq!(chip8.reg[2], 0xAA);
+        assert_eq!(chip8.reg_i, 3);
+    }
+
+    #[test]
+    fn test_op_fx55() {
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0xA300, 0xF255]).unwrap();
+        chip8.reg[..3].copy_from_slice(&[1, 2, 3]);
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(chip8.memory[0x300..0x304], [1, 2, 3, 0]);
+        assert_eq!(chip8.reg_i, 0x303);
+    }
+
+    #[test]
+    fn test_op_fx55_round_trip() {
+        // Stores V0-V3, then loads them back after clearing them
+        let mut chip8 = Chip8::new();
+        chip8.quirks.load_store = quirks::LoadStore::None;
+        chip8.load_vec(vec![0xA400, 0xF355, 0x6000, 0x6100, 0x6200, 0x6300, 0xF365]).unwrap();
+        chip8.reg[..5].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF, 0x55]);
+        chip8.tick(None);
+        chip8.tick(None);
+        assert_eq!(chip8.memory[0x400..0x405], [0xDE, 0xAD, 0xBE, 0xEF, 0]);
+        assert_eq!(chip8.reg_read, vec![0, 1, 2, 3]);
+        for _ in 0..5 {
+            chip8.tick(None);
+        }
+        assert_eq!(chip8.reg[..5], [0xDE, 0xAD, 0xBE, 0xEF, 0x55]);
+    }
+
+    #[test]
+    fn test_load_store_quirk() {
+        for (load_store, reg_i) in [
+            (quirks::LoadStore::Increment, 0x303),
+            (quirks::LoadStore::IncrementMinusOne, 0x302),
+            (quirks::LoadStore::None, 0x300),
+        ] {
+            for opcode in [0xF255, 0xF265] {
+                let mut chip8 = Chip8::new();
+                chip8.quirks.load_store = load_store;
+                chip8.load_vec(vec![0xA300, opcode]).unwrap();
+                chip8.tick(None);
+                chip8.tick(None);
+                assert_eq!(chip8.reg_i, reg_i, "{:?} {:04X}", load_store, opcode);
+            }
+        }
+        assert_eq!(Quirks::from_profile(quirks::Profile::Chip48).load_store, quirks::LoadStore::IncrementMinusOne);
     }
 }
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::fs;
+use log::{error, warn};
+
+pub const CONFIG_FILE: &str = "chip8_rust.cfg";
+
+/// Settings kept between runs, stored as `key = value` lines
+pub struct Config {
+    filename: String,
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    // A missing file just means nothing has been saved yet
+    pub fn load(filename: &str) -> Self {
+        let values = match fs::read_to_string(filename) {
+            Ok(text) => parse(&text),
+            Err(_) => BTreeMap::new(),
+        };
+        Config {
+            filename: filename.to_owned(),
+            values,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    // Updates a setting and writes the file straight away
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_owned(), value.to_owned());
+        if let Err(e) = fs::write(&self.filename, self.to_text()) {
+            error!("Unable to save settings to '{}': {}", self.filename, e);
+        }
+    }
+
+    fn to_text(&self) -> String {
+        self.values.iter().map(|(key, value)| format!("{} = {}\n", key, value)).collect()
+    }
+}
+
+pub fn parse(text: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        match line.split_once('=') {
+            Some((key, value)) => {
+                values.insert(key.trim().to_owned(), value.trim().to_owned());
+            }
+            None => warn!("Ignoring bad settings line '{}'", line),
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let filename = std::env::temp_dir().join("chip8_test_config.cfg");
+        let filename = filename.to_str().unwrap();
+        let _ = fs::remove_file(filename);
+        let mut config = Config::load(filename);
+        assert_eq!(config.get("keypad_layout"), None);
+        config.set("keypad_layout", "Numpad");
+        assert_eq!(Config::load(filename).get("keypad_layout"), Some("Numpad"));
+        fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_config_parse_skips_comments() {
+        let values = parse("# settings\nspeed = 8\nnonsense\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["speed"], "8");
+    }
+}
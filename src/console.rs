@@ -0,0 +1,195 @@
+// A command line for poking at the machine from the keyboard: peek, poke, set, pc, regs and break
+
+use crate::chip8::Chip8;
+use crate::hooks::{PcAction, PcHook};
+
+// Lines of output kept before the oldest are dropped
+const SCROLLBACK_LINES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    V(usize),
+    I,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Peek(usize),
+    Poke(usize, u8),
+    Set(Register, u16),
+    Pc(usize),
+    Regs,
+    Break(usize),
+}
+
+pub const HELP: &str = "peek ADDR, poke ADDR BYTE, set VX|I VALUE, pc ADDR, regs, break ADDR";
+
+// Numbers are decimal unless they start with 0x
+fn parse_number(text: &str) -> Result<usize, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("Bad number '{}'", text))
+}
+
+fn parse_byte(text: &str) -> Result<u8, String> {
+    let value = parse_number(text)?;
+    u8::try_from(value).map_err(|_| format!("{} doesn't fit in a byte", text))
+}
+
+fn parse_register(text: &str) -> Result<Register, String> {
+    let lower = text.to_ascii_lowercase();
+    if lower == "i" {
+        return Ok(Register::I);
+    }
+    lower.strip_prefix('v')
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+        .map(Register::V)
+        .ok_or(format!("Bad register '{}', expected V0-VF or I", text))
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+        return Err("Empty command".to_owned());
+    };
+    let command = match (name.to_ascii_lowercase().as_str(), args) {
+        ("peek", [address]) => Command::Peek(parse_number(address)?),
+        ("poke", [address, value]) => Command::Poke(parse_number(address)?, parse_byte(value)?),
+        ("set", [reg, value]) => {
+            let reg = parse_register(reg)?;
+            let value = parse_number(value)?;
+            let max = if reg == Register::I { 0xFFFF } else { 0xFF };
+            if value > max {
+                return Err(format!("{} is too big for {}", value, args[0]));
+            }
+            Command::Set(reg, value as u16)
+        }
+        ("pc", [address]) => Command::Pc(parse_number(address)?),
+        ("regs", []) => Command::Regs,
+        ("break", [address]) => Command::Break(parse_number(address)?),
+        ("peek" | "poke" | "set" | "pc" | "regs" | "break", _) => {
+            return Err(format!("Wrong arguments for '{}', usage: {}", name, HELP));
+        }
+        _ => return Err(format!("Unknown command '{}', try: {}", name, HELP)),
+    };
+    Ok(command)
+}
+
+// Carries out a command, returning what to print
+pub fn run(command: Command, chip8: &mut Chip8, hooks: &mut Vec<PcHook>) -> Result<String, String> {
+    let in_memory = |address: usize| {
+        if address < chip8.memory().len() { Ok(address) } else { Err(format!("{:#05x} is outside memory", address)) }
+    };
+    match command {
+        Command::Peek(address) => {
+            let address = in_memory(address)?;
+            Ok(format!("{:03X}: {:02X}", address, chip8.memory()[address]))
+        }
+        Command::Poke(address, value) => {
+            chip8.patch(address, &[value]).map_err(|e| e.to_string())?;
+            Ok(format!("{:03X} = {:02X}", address, value))
+        }
+        Command::Set(Register::V(reg), value) => {
+            chip8.reg[reg] = value as u8;
+            chip8.reg_write.push(reg);
+            Ok(format!("V{:X} = {:02X}", reg, value))
+        }
+        Command::Set(Register::I, value) => {
+            chip8.reg_i = value;
+            Ok(format!("I = {:03X}", value))
+        }
+        Command::Pc(address) => {
+            chip8.pc = in_memory(address)?;
+            Ok(format!("PC = {:03X}", address))
+        }
+        Command::Regs => {
+            let reg: Vec<String> = chip8.reg.iter().map(|value| format!("{:02X}", value)).collect();
+            Ok(format!("PC {:03X}: V0-VF {} I {:03X}", chip8.pc, reg.join(" "), chip8.reg_i))
+        }
+        Command::Break(address) => {
+            let pc = in_memory(address)?;
+            hooks.push(PcHook { pc, action: PcAction::Pause, armed: true });
+            Ok(format!("Pausing at {:03X}", pc))
+        }
+    }
+}
+
+/// The input line and everything echoed so far
+pub struct Console {
+    pub input: String,
+    pub scrollback: Vec<String>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console { input: String::new(), scrollback: Vec::new() }
+    }
+
+    // Runs the input line and clears it, echoing both the command and its result
+    pub fn submit(&mut self, chip8: &mut Chip8, hooks: &mut Vec<PcHook>) {
+        let line = std::mem::take(&mut self.input);
+        if line.trim().is_empty() {
+            return;
+        }
+        let output = parse(&line).and_then(|command| run(command, chip8, hooks));
+        self.push(format!("> {}", line.trim()));
+        self.push(output.unwrap_or_else(|e| e));
+    }
+
+    fn push(&mut self, line: String) {
+        if self.scrollback.len() == SCROLLBACK_LINES {
+            self.scrollback.remove(0);
+        }
+        self.scrollback.push(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("peek 0x300"), Ok(Command::Peek(0x300)));
+        assert_eq!(parse("poke 0x300 0xAB"), Ok(Command::Poke(0x300, 0xAB)));
+        assert_eq!(parse("POKE 768 171"), Ok(Command::Poke(0x300, 0xAB)));
+        assert_eq!(parse("set v3 5"), Ok(Command::Set(Register::V(3), 5)));
+        assert_eq!(parse("set VF 0xff"), Ok(Command::Set(Register::V(15), 0xFF)));
+        assert_eq!(parse("set i 0x250"), Ok(Command::Set(Register::I, 0x250)));
+        assert_eq!(parse("  pc 0x250 "), Ok(Command::Pc(0x250)));
+        assert_eq!(parse("regs"), Ok(Command::Regs));
+        assert_eq!(parse("break 0x2A0"), Ok(Command::Break(0x2A0)));
+
+        assert!(parse("").is_err());
+        assert!(parse("jump 0x200").is_err());
+        assert!(parse("peek").is_err());
+        assert!(parse("regs 1").is_err());
+        assert!(parse("poke 0x300 256").is_err());
+        assert!(parse("set v3 0x100").is_err());
+        assert!(parse("set vg 1").is_err());
+        assert!(parse("pc 0xZZ").is_err());
+    }
+
+    #[test]
+    fn test_console_runs_commands() {
+        let mut chip8 = Chip8::new();
+        let mut hooks = Vec::new();
+        let mut console = Console::new();
+        for line in ["poke 0x300 0xAB", "peek 0x300", "set v3 5", "pc 0x250", "break 0x2A0", "poke 0 1", "nope"] {
+            console.input = line.to_owned();
+            console.submit(&mut chip8, &mut hooks);
+        }
+        assert_eq!(chip8.memory()[0x300], 0xAB);
+        assert_eq!(chip8.reg[3], 5);
+        assert_eq!(chip8.pc, 0x250);
+        assert_eq!(hooks, vec![PcHook { pc: 0x2A0, action: PcAction::Pause, armed: true }]);
+        assert_eq!(console.scrollback[0], "> poke 0x300 0xAB");
+        assert_eq!(console.scrollback[3], "300: AB");
+        assert!(console.scrollback[11].starts_with("Address"));
+        assert!(console.scrollback[13].starts_with("Unknown command"));
+        assert!(console.input.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+// Opcode coverage for the test suite.
+// Every implemented opcode reports itself through record(), which appends its pattern to
+// the file named by CHIP8_COVERAGE_FILE. test_opcode_coverage re-runs the rest of the test
+// binary with that set and fails if an implemented opcode was never executed.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::Command;
+use std::sync::Mutex;
+use crate::chip8::opcodes::{OPCODES, UNIMPLEMENTED};
+
+const COVERAGE_FILE_VAR: &str = "CHIP8_COVERAGE_FILE";
+
+// Patterns already written by this process, so hot loops don't reopen the file every instruction
+static RECORDED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+pub fn record(pattern: &'static str) {
+    if !RECORDED.lock().unwrap().insert(pattern) {
+        return;
+    }
+    if let Ok(filename) = env::var(COVERAGE_FILE_VAR) {
+        let mut file = OpenOptions::new().create(true).append(true).open(filename).unwrap();
+        writeln!(file, "{}", pattern).unwrap();
+    }
+}
+
+#[test]
+fn test_opcode_coverage() {
+    if env::var(COVERAGE_FILE_VAR).is_ok() {
+        return;
+    }
+    let filename = env::temp_dir().join(format!("chip8_coverage_{}.txt", std::process::id()));
+    let _ = fs::remove_file(&filename);
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--skip", "test_opcode_coverage", "--quiet"])
+        .env(COVERAGE_FILE_VAR, &filename)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Test run for coverage failed:\n{}", String::from_utf8_lossy(&output.stdout));
+    let text = fs::read_to_string(&filename).unwrap_or_default();
+    fs::remove_file(&filename).ok();
+    let executed: BTreeSet<&str> = text.lines().collect();
+
+    let untested: Vec<&str> = OPCODES
+        .iter()
+        .map(|info| info.pattern)
+        .filter(|pattern| !executed.contains(pattern) && !UNIMPLEMENTED.contains(pattern))
+        .collect();
+    let stale: Vec<&str> = UNIMPLEMENTED.iter().copied().filter(|pattern| executed.contains(pattern)).collect();
+    println!(
+        "Opcode coverage: {}/{} executed, {} unimplemented",
+        executed.len(),
+        OPCODES.len(),
+        UNIMPLEMENTED.len()
+    );
+    println!("Untested opcodes: {}", if untested.is_empty() { "none".to_owned() } else { untested.join(" ") });
+    assert!(untested.is_empty(), "Opcodes with no test: {}", untested.join(" "));
+    assert!(stale.is_empty(), "Opcodes now implemented, remove from UNIMPLEMENTED: {}", stale.join(" "));
+}
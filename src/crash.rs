@@ -0,0 +1,68 @@
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::chip8::Chip8;
+use crate::report::Report;
+
+// A copy of the machine taken every frame, so there's something to save if a panic happens mid-frame
+static LAST_STATE: Mutex<Option<Report>> = Mutex::new(None);
+
+pub fn update(chip8: &Chip8, speed: u32) {
+    if let Ok(mut state) = LAST_STATE.lock() {
+        *state = Some(Report::capture(chip8, speed, Vec::new()));
+    }
+}
+
+// Writes the last known state to a crash file in `dir` on any panic, then carries on with the usual hook
+pub fn install(dir: PathBuf) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(filename) = write_crash_file(&dir, &info.to_string()) {
+            eprintln!("Saved the emulator state to '{}'", filename.display());
+        }
+        default_hook(info);
+    }));
+}
+
+// Uses the bug report format, so a crash file can be opened with "Load bug report"
+fn write_crash_file(dir: &Path, message: &str) -> Option<PathBuf> {
+    // try_lock as the panic may have come from inside update
+    let state = LAST_STATE.try_lock().ok()?;
+    let report = state.as_ref()?;
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let filename = dir.join(format!("chip8_crash_{}.txt", secs));
+    let text = format!("{}log [PANIC] {}\n", report.to_text(), message.replace('\n', " "));
+    std::fs::write(&filename, text).ok()?;
+    Some(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_writes_crash_file() {
+        let dir = std::env::temp_dir().join(format!("chip8_crash_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6A45]).unwrap();
+        chip8.tick(None);
+        update(&chip8, 6);
+
+        install(dir.clone());
+        let result = panic::catch_unwind(|| panic!("deliberate test panic"));
+        let _ = panic::take_hook();
+        assert!(result.is_err());
+
+        let crash_file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let report = Report::load(crash_file.to_str().unwrap()).unwrap();
+        assert_eq!(report.state.reg[0xA], 0x45);
+        assert_eq!(report.rom_hash, chip8.rom_hash());
+        assert_eq!(report.pc_history, vec![0x200]);
+        assert!(report.log.last().unwrap().contains("deliberate test panic"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+// Formats ROMs as source code arrays, so they can be baked into a binary with no file to load
+
+const BYTES_PER_LINE: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrayFormat {
+    Rust,
+    C,
+}
+
+// Turns a file name like "test_opcode.ch8" into TEST_OPCODE, usable in either language
+pub fn array_name(filename: &str) -> String {
+    let stem = std::path::Path::new(filename).file_stem().and_then(|stem| stem.to_str()).unwrap_or("rom");
+    let name: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("ROM_{}", name)
+    } else {
+        name
+    }
+}
+
+pub fn rom_to_array(name: &str, bytes: &[u8], format: ArrayFormat) -> String {
+    let body: Vec<String> = bytes
+        .chunks(BYTES_PER_LINE)
+        .map(|line| format!("    {},", line.iter().map(|b| format!("0x{:02X}", b)).collect::<Vec<_>>().join(", ")))
+        .collect();
+    let (open, close) = match format {
+        ArrayFormat::Rust => (format!("pub const {}: [u8; {}] = [", name, bytes.len()), "];"),
+        ArrayFormat::C => (format!("const unsigned char {}[{}] = {{", name.to_ascii_lowercase(), bytes.len()), "};"),
+    };
+    let mut lines = vec![open];
+    lines.extend(body);
+    lines.push(close.to_owned());
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads the bytes back out of an array, checking the declared length matches
+    fn parse_array(text: &str) -> Vec<u8> {
+        // Between the first brackets is either "u8; N" or just "N"
+        let declared = text.split('[').nth(1).unwrap().split(']').next().unwrap();
+        let declared: usize = declared.rsplit(' ').next().unwrap().parse().unwrap();
+        let body = text.split_once('\n').unwrap().1;
+        let bytes: Vec<u8> = body
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|token| token.strip_prefix("0x"))
+            .map(|hex| u8::from_str_radix(hex, 16).unwrap())
+            .collect();
+        assert_eq!(bytes.len(), declared);
+        bytes
+    }
+
+    #[test]
+    fn test_rom_to_array_round_trip() {
+        let rom: Vec<u8> = (0..=40).collect();
+        for format in [ArrayFormat::Rust, ArrayFormat::C] {
+            let text = rom_to_array("TEST", &rom, format);
+            assert_eq!(parse_array(&text), rom);
+            assert!(text.lines().all(|line| line.len() <= 80));
+        }
+        assert!(rom_to_array("TEST", &rom, ArrayFormat::Rust).starts_with("pub const TEST: [u8; 41] = ["));
+        assert!(rom_to_array("TEST", &rom, ArrayFormat::C).starts_with("const unsigned char test[41] = {"));
+    }
+
+    #[test]
+    fn test_array_name() {
+        assert_eq!(array_name("roms/test-opcode.ch8"), "TEST_OPCODE");
+        assert_eq!(array_name("15puzzle.ch8"), "ROM_15PUZZLE");
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use std::io;
+use super::stack::StackError;
+
+/// Everything that can go wrong loading or running a program
+#[derive(Debug)]
+pub enum Chip8Error {
+    // The ROM doesn't fit in the memory after 0x200
+    RomTooLarge { size: usize, space: usize },
+    Io(io::Error),
+    StackOverflow,
+    StackUnderflow,
+    UnknownOpcode(u16),
+    // An access outside memory, or a write to memory that has to be left alone
+    AddressOutOfRange(usize),
+    // A write below 0x200 while protect_low_memory is set, from the instruction at pc
+    ProtectedWrite { pc: usize, address: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::RomTooLarge { size, space } => write!(f, "ROM is {} bytes but only {} fit in memory", size, space),
+            Chip8Error::Io(e) => write!(f, "{}", e),
+            Chip8Error::StackOverflow => write!(f, "Stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "Stack underflow"),
+            Chip8Error::UnknownOpcode(code) => write!(f, "Unknown opcode {:04X}", code),
+            Chip8Error::AddressOutOfRange(address) => write!(f, "Address {:#05x} is out of range", address),
+            Chip8Error::ProtectedWrite { pc, address } => {
+                write!(f, "Write to protected address {:#05x} at {:#05x}", address, pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Self {
+        Chip8Error::Io(e)
+    }
+}
+
+impl From<StackError> for Chip8Error {
+    fn from(e: StackError) -> Self {
+        match e {
+            StackError::Overflow => Chip8Error::StackOverflow,
+            StackError::Underflow => Chip8Error::StackUnderflow,
+        }
+    }
+}
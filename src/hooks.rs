@@ -0,0 +1,92 @@
+// Actions attached to PC addresses, run when the PC gets there, before the instruction at it,
+// and breakpoints on kinds of instruction
+
+use crate::chip8::opcodes;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PcAction {
+    // Logs the registers
+    Log,
+    SetRegister { reg: usize, value: u8 },
+    Pause,
+    // Saves the display as a PNG
+    Screenshot,
+    // Snapshots the machine into a save state slot, once per arming
+    SaveState { slot: usize },
+}
+
+impl PcAction {
+    pub const ALL: [PcAction; 5] = [
+        PcAction::Log,
+        PcAction::SetRegister { reg: 0, value: 0 },
+        PcAction::Pause,
+        PcAction::Screenshot,
+        PcAction::SaveState { slot: 0 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PcAction::Log => "Log registers",
+            PcAction::SetRegister { .. } => "Set register",
+            PcAction::Pause => "Pause",
+            PcAction::Screenshot => "Screenshot",
+            PcAction::SaveState { .. } => "Save state",
+        }
+    }
+
+    // Whether the hook disarms itself after running, rather than running every time
+    pub fn one_shot(&self) -> bool {
+        matches!(self, PcAction::SaveState { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            PcAction::SetRegister { reg, value } => format!("V{:X} = {:02X}", reg, value),
+            PcAction::SaveState { slot } => format!("Save state to slot {}", slot + 1),
+            _ => self.name().to_owned(),
+        }
+    }
+}
+
+// A kind of instruction to pause before, wherever it is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpcodeBreak {
+    // An opcode pattern from the table, like "DXYN"
+    Pattern(&'static str),
+    Unimplemented,
+}
+
+impl OpcodeBreak {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpcodeBreak::Pattern(pattern) => pattern,
+            OpcodeBreak::Unimplemented => "Unimplemented",
+        }
+    }
+
+    pub fn matches(&self, code: u16) -> bool {
+        match self {
+            OpcodeBreak::Pattern(pattern) => opcodes::describe(code).0 == *pattern,
+            OpcodeBreak::Unimplemented => opcodes::is_unimplemented(code),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PcHook {
+    pub pc: usize,
+    pub action: PcAction,
+    // Cleared once a one-shot action has run, until it's armed again
+    pub armed: bool,
+}
+
+// The actions to run now the PC is at this address, in the order they were added,
+// disarming the one-shot ones
+pub fn fire_at(hooks: &mut [PcHook], pc: usize) -> Vec<PcAction> {
+    let mut actions = Vec::new();
+    for hook in hooks.iter_mut().filter(|hook| hook.pc == pc && hook.armed) {
+        actions.push(hook.action);
+        hook.armed = !hook.action.one_shot();
+    }
+    actions
+}
@@ -0,0 +1,172 @@
+use macroquad::prelude::*;
+use crate::chip8::Chip8;
+
+// The usual layout mapping the 4x4 hex keypad onto the left of a QWERTY keyboard
+const QWERTY_KEYMAP: [(KeyCode, u8); 16] = [
+    (KeyCode::Key1, 0x1), (KeyCode::Key2, 0x2), (KeyCode::Key3, 0x3), (KeyCode::Key4, 0xC),
+    (KeyCode::Q, 0x4), (KeyCode::W, 0x5), (KeyCode::E, 0x6), (KeyCode::R, 0xD),
+    (KeyCode::A, 0x7), (KeyCode::S, 0x8), (KeyCode::D, 0x9), (KeyCode::F, 0xE),
+    (KeyCode::Z, 0xA), (KeyCode::X, 0x0), (KeyCode::C, 0xB), (KeyCode::V, 0xF),
+];
+
+// The keypad's shape laid over the numpad, with 1-2-3 on the top row like a telephone
+const TELEPHONE_KEYMAP: [(KeyCode, u8); 16] = [
+    (KeyCode::Kp7, 0x1), (KeyCode::Kp8, 0x2), (KeyCode::Kp9, 0x3), (KeyCode::KpDivide, 0xC),
+    (KeyCode::Kp4, 0x4), (KeyCode::Kp5, 0x5), (KeyCode::Kp6, 0x6), (KeyCode::KpMultiply, 0xD),
+    (KeyCode::Kp1, 0x7), (KeyCode::Kp2, 0x8), (KeyCode::Kp3, 0x9), (KeyCode::KpSubtract, 0xE),
+    (KeyCode::Kp0, 0xA), (KeyCode::KpDecimal, 0x0), (KeyCode::KpEnter, 0xB), (KeyCode::KpAdd, 0xF),
+];
+
+// Each digit on its own numpad key, with the operators standing in for A-F
+const NUMPAD_KEYMAP: [(KeyCode, u8); 16] = [
+    (KeyCode::Kp0, 0x0), (KeyCode::Kp1, 0x1), (KeyCode::Kp2, 0x2), (KeyCode::Kp3, 0x3),
+    (KeyCode::Kp4, 0x4), (KeyCode::Kp5, 0x5), (KeyCode::Kp6, 0x6), (KeyCode::Kp7, 0x7),
+    (KeyCode::Kp8, 0x8), (KeyCode::Kp9, 0x9), (KeyCode::KpDivide, 0xA), (KeyCode::KpMultiply, 0xB),
+    (KeyCode::KpSubtract, 0xC), (KeyCode::KpAdd, 0xD), (KeyCode::KpEnter, 0xE), (KeyCode::KpDecimal, 0xF),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeypadLayout {
+    Qwerty,
+    Telephone,
+    Numpad,
+}
+
+impl KeypadLayout {
+    pub const ALL: [KeypadLayout; 3] = [KeypadLayout::Qwerty, KeypadLayout::Telephone, KeypadLayout::Numpad];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeypadLayout::Qwerty => "QWERTY",
+            KeypadLayout::Telephone => "Telephone",
+            KeypadLayout::Numpad => "Numpad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeypadLayout> {
+        KeypadLayout::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+
+    pub fn keymap(&self) -> &'static [(KeyCode, u8); 16] {
+        match self {
+            KeypadLayout::Qwerty => &QWERTY_KEYMAP,
+            KeypadLayout::Telephone => &TELEPHONE_KEYMAP,
+            KeypadLayout::Numpad => &NUMPAD_KEYMAP,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyRepeat {
+    // A held key stays pressed every frame
+    Held,
+    // A key only counts as pressed on the frame it goes down, for games that expect separate presses
+    Edge,
+}
+
+impl KeyRepeat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyRepeat::Held => "Held",
+            KeyRepeat::Edge => "Edge",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyRepeat> {
+        [KeyRepeat::Held, KeyRepeat::Edge].into_iter().find(|repeat| repeat.name() == name)
+    }
+}
+
+/// Turns the physical keyboard into keypad presses each frame
+pub struct KeyInput {
+    pub repeat: KeyRepeat,
+    previous: [bool; 16],
+}
+
+impl KeyInput {
+    pub fn new(repeat: KeyRepeat) -> Self {
+        KeyInput {
+            repeat,
+            previous: [false; 16],
+        }
+    }
+
+    pub fn update_keys(&mut self, chip8: &mut Chip8, layout: KeypadLayout) {
+        let mut down = [false; 16];
+        for (code, key) in layout.keymap() {
+            down[*key as usize] |= is_key_down(*code);
+        }
+        self.apply(chip8, down);
+    }
+
+    // Presses and releases keypad keys given which keys are physically down this frame
+    fn apply(&mut self, chip8: &mut Chip8, down: [bool; 16]) {
+        for (key, is_down) in down.iter().enumerate() {
+            let pressed = match self.repeat {
+                KeyRepeat::Held => *is_down,
+                KeyRepeat::Edge => *is_down && !self.previous[key],
+            };
+            if pressed {
+                chip8.press_key(key as u8);
+            } else {
+                chip8.release_key(key as u8);
+            }
+        }
+        self.previous = down;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_for(layout: KeypadLayout, code: KeyCode) -> Option<u8> {
+        layout.keymap().iter().find(|(c, _)| *c == code).map(|(_, key)| *key)
+    }
+
+    #[test]
+    fn test_layouts_map_expected_keys() {
+        assert_eq!(key_for(KeypadLayout::Qwerty, KeyCode::Key4), Some(0xC));
+        assert_eq!(key_for(KeypadLayout::Qwerty, KeyCode::X), Some(0x0));
+        assert_eq!(key_for(KeypadLayout::Telephone, KeyCode::Kp7), Some(0x1));
+        assert_eq!(key_for(KeypadLayout::Telephone, KeyCode::Kp3), Some(0x9));
+        assert_eq!(key_for(KeypadLayout::Telephone, KeyCode::KpDecimal), Some(0x0));
+        assert_eq!(key_for(KeypadLayout::Numpad, KeyCode::Kp7), Some(0x7));
+        assert_eq!(key_for(KeypadLayout::Numpad, KeyCode::KpDecimal), Some(0xF));
+        assert_eq!(key_for(KeypadLayout::Numpad, KeyCode::Q), None);
+    }
+
+    fn held_key_frames(repeat: KeyRepeat) -> Vec<bool> {
+        let mut input = KeyInput::new(repeat);
+        let mut chip8 = Chip8::new();
+        let mut down = [false; 16];
+        let mut frames = Vec::new();
+        // Hold 5 for three frames, let go for one, then press it again
+        for held in [true, true, true, false, true] {
+            down[0x5] = held;
+            input.apply(&mut chip8, down);
+            frames.push(chip8.is_key_down(0x5));
+        }
+        frames
+    }
+
+    #[test]
+    fn test_held_key_repeats() {
+        assert_eq!(held_key_frames(KeyRepeat::Held), vec![true, true, true, false, true]);
+    }
+
+    #[test]
+    fn test_held_key_edge_only() {
+        assert_eq!(held_key_frames(KeyRepeat::Edge), vec![true, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_layouts_cover_every_key_once() {
+        for layout in KeypadLayout::ALL {
+            let mut keys: Vec<u8> = layout.keymap().iter().map(|(_, key)| *key).collect();
+            keys.sort();
+            assert_eq!(keys, (0..16).collect::<Vec<u8>>(), "{}", layout.name());
+            assert_eq!(KeypadLayout::from_name(layout.name()), Some(layout));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// A line protocol for driving the keypad from another program, through stdin or a named pipe.
+//
+// Each line is one 60Hz frame and lists the keys held during it as hex digits, in any order:
+//
+//     5        key 5 held
+//     4 6      keys 4 and 6 held (spaces are optional, so "46" is the same)
+//     -        no keys held
+//     # text   a comment, which doesn't use up a frame
+//
+// Blank lines are skipped like comments, so a frame with nothing held has to be written as "-".
+
+use std::io::BufRead;
+
+// The keys held for one protocol line, or None if it's a comment or blank
+pub fn parse_line(line: &str) -> Result<Option<[bool; 16]>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut keys = [false; 16];
+    if line == "-" {
+        return Ok(Some(keys));
+    }
+    for c in line.chars().filter(|c| !c.is_whitespace()) {
+        let key = c.to_digit(16).ok_or(format!("Bad key '{}' in '{}'", c, line))?;
+        keys[key as usize] = true;
+    }
+    Ok(Some(keys))
+}
+
+/// Reads frames of key states from a protocol stream until it ends
+pub struct KeyStream<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> KeyStream<R> {
+    pub fn new(reader: R) -> Self {
+        KeyStream { reader }
+    }
+}
+
+impl<R: BufRead> Iterator for KeyStream<R> {
+    type Item = Result<[bool; 16], String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => match parse_line(&line) {
+                    Ok(Some(keys)) => return Some(Ok(keys)),
+                    Ok(None) => continue,
+                    Err(e) => return Some(Err(e)),
+                },
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystream_protocol() {
+        let text = "# Walk right then jump\n6\n\n6 5\n-\n0F\n";
+        let frames: Vec<[bool; 16]> = KeyStream::new(text.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].iter().filter(|down| **down).count(), 1);
+        assert!(frames[0][6]);
+        assert!(frames[1][5] && frames[1][6]);
+        assert_eq!(frames[2], [false; 16]);
+        assert!(frames[3][0x0] && frames[3][0xF]);
+        assert!(parse_line("5G").is_err());
+        assert!(KeyStream::new("1\nxyz\n".as_bytes()).nth(1).unwrap().is_err());
+    }
+}
@@ -0,0 +1,8 @@
+// The emulator core, which only needs log and rand so it can be embedded without the GUI
+pub mod chip8;
+pub mod embed;
+pub mod keystream;
+#[cfg(test)]
+mod coverage;
+#[cfg(test)]
+mod golden;
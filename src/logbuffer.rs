@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const CAPACITY: usize = 200;
+
+static BUFFER: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// Wraps env_logger so recent messages can be shown in the UI and attached to bug reports
+struct BufferedLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata) || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        push(format!("[{}] {}", record.level(), record.args()));
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter().max(LevelFilter::Warn);
+    log::set_boxed_logger(Box::new(BufferedLogger { inner }))
+        .expect("Logger already initialised");
+    log::set_max_level(max_level);
+}
+
+pub fn push(message: String) {
+    let mut buffer = BUFFER.lock().unwrap();
+    if buffer.len() == CAPACITY {
+        buffer.pop_front();
+    }
+    buffer.push_back(message);
+}
+
+pub fn recent() -> Vec<String> {
+    BUFFER.lock().unwrap().iter().cloned().collect()
+}
@@ -1,5 +1,21 @@
-mod chip8;
 mod app;
+mod audio;
+mod calltree;
+mod config;
+mod console;
+mod crash;
+mod hooks;
+mod input;
+mod logbuffer;
+mod pacer;
+mod profile;
+mod recording;
+mod report;
+mod suite;
+mod timeline;
+mod tracer;
+mod watcher;
+mod window;
 
 use std::fmt::format;
 use macroquad::prelude::*;
@@ -7,8 +23,10 @@ use clap::Parser;
 use egui::{Slider, Ui};
 use egui::Color32;
 use egui::RichText;
-use chip8::Chip8;
-use app::App;
+use chip8_rust::chip8;
+use chip8::{Chip8, DirtyRect, Frame};
+use app::{App, DisplayScaling, DisplayTransform};
+use config::Config;
 use std::time::Duration;
 use std::{thread, time};
 
@@ -17,8 +35,24 @@ use std::{thread, time};
 #[clap(author, version, about, long_about = None)]
 struct Args {
     /// Path to the ROM file to run
-    #[clap(required = true)]
-    romfile: String,
+    #[clap(required_unless_present_any = &["hex", "test-suite"])]
+    romfile: Option<String>,
+
+    /// Run a program given as hex opcodes instead, e.g. --hex "00E0 A22A 600C"
+    #[clap(long, conflicts_with = "romfile")]
+    hex: Option<String>,
+
+    /// Run every .ch8 ROM in a directory without a window and write a report
+    #[clap(long, value_name = "DIR", conflicts_with_all = &["romfile", "hex"])]
+    test_suite: Option<String>,
+
+    /// Instructions each ROM runs for in --test-suite mode
+    #[clap(long, default_value_t = suite::DEFAULT_CYCLES)]
+    cycles: u64,
+
+    /// Split ROMs too big for memory into banks, selected by writing to 0xFFF
+    #[clap(long)]
+    bank_switching: bool,
 }
 
 pub struct Environment {
@@ -26,85 +60,252 @@ pub struct Environment {
 }
 
 fn window_conf() -> Conf {
+    // Reopen at the size the window was closed at
+    let config = Config::load(config::CONFIG_FILE);
+    let (window_width, window_height) = config.get("window_size").and_then(parse_window_size).unwrap_or((960, 600));
     Conf {
         window_title: "CHIP-8".to_owned(),
         high_dpi: true,
         window_resizable: true,
-        window_width: 960,
-        window_height: 600,
+        window_width,
+        window_height,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+fn main() {
     // Parse command line arguments
     let args = Args::parse();
 
     // Initialise the logger
-    env_logger::init();
+    logbuffer::init();
+
+    // The test suite runs headless, so it has to start before the window opens
+    if let Some(dir) = &args.test_suite {
+        std::process::exit(suite::main(dir, args.cycles));
+    }
+    macroquad::Window::from_config(window_conf(), run(args));
+}
+
+async fn run(args: Args) {
+    crash::install(std::path::PathBuf::from("."));
 
     // Set up CHIP-8 and load the ROM
     let mut keypress: Option<u8> = None;
     let mut app = App::new();
-    app.chip8.load_rom(&args.romfile);
+    app.chip8.bank_switching = args.bank_switching;
+    match (&args.romfile, &args.hex) {
+        (Some(romfile), _) => app.load_rom(romfile),
+        (None, Some(hex)) => {
+            if let Err(e) = app.load_hex(hex) {
+                eprintln!("Unable to load --hex program: {}", e);
+                std::process::exit(1);
+            }
+        }
+        (None, None) => unreachable!(),
+    }
 
     // Set up texture for macroquad
-    let mut texture = pixels_to_texture2d(&app.chip8.display, &app.fg_color, &app.bg_color);
+    let mut texture = frame_to_texture2d(&app.chip8.frame(), &app.fg_color, &app.bg_color, app.display_transform);
     texture.set_filter(FilterMode::Nearest);
+    let mut compare_texture: Option<Texture2D> = None;
+
+    // Closing the window is handled in the loop so recordings can be finalised
+    prevent_quit();
+    app.restore_window_position();
 
     'running: loop {
+        if is_quit_requested() {
+            app.shutdown();
+            break 'running;
+        }
+
+        let displays = if app.compare.is_some() { 2 } else { 1 };
+        let camera = display_camera(app.display_scaling, displays, app.display_border);
+        app.hovered_pixel = pixel_under_mouse(&camera, app.chip8.display_dimensions(), app.display_transform);
+
         egui_macroquad::ui(|egui_ctx| {
+            if egui_ctx.is_pointer_over_area() {
+                app.hovered_pixel = None;
+            }
             setup_custom_fonts(&egui_ctx);
+            egui_ctx.set_visuals(app.visuals());
             app.show_main_menubar(&egui_ctx);
+            app.show_status_bar(&egui_ctx);
             app.show_general_state(&egui_ctx);
             app.show_controls(&egui_ctx);
+            app.show_disassembly(&egui_ctx);
+            app.show_audio_visualizer(&egui_ctx);
+            app.show_quirks(&egui_ctx);
+            app.show_opcode(&egui_ctx);
+            app.show_compare(&egui_ctx);
+            app.show_memory(&egui_ctx);
+            app.show_breakpoints(&egui_ctx);
+            app.show_memory_bitmap(&egui_ctx);
+            app.show_opcode_status(&egui_ctx);
+            app.show_call_tree(&egui_ctx);
+            app.show_timeline(&egui_ctx);
+            app.show_console(&egui_ctx);
         });
 
+        app.calculate_ops_and_draws();
+        crash::update(&app.chip8, app.speed);
+        app.poll_rom_watcher();
+        if is_key_pressed(KeyCode::F12) {
+            app.save_report();
+        }
+        // A recording being played back takes over from the keyboard
+        if app.pause_execution || !app.playing_input() {
+            app.key_input.update_keys(&mut app.chip8, app.keypad_layout);
+        }
+        // 60Hz frames due by the wall clock, counted while paused too so unpausing doesn't catch up
+        let frames = app.frame_clock.frames_due(get_time(), app.catch_up);
+        // Audio is generated per 60Hz frame as it runs, the visualizer just goes quiet while paused
+        if app.pause_execution {
+            app.beeper.generate_frame(false);
+        }
+
         // If not paused or paused but step requested
         if !app.pause_execution || (app.pause_execution && app.step) {
             if !app.pause_execution { // Execute normally
-                for i in 0..app.speed {
-                    app.chip8.tick(keypress);
-                    app.ops_per_sec += 1;
-                }
+                // Locked to 60Hz every frame gets exactly speed instructions, otherwise they're paced
+                // by the time since the last refresh
+                let due = if app.lock_60hz {
+                    app.speed * frames
+                } else {
+                    app.pacer.instructions_due(app.target_ips(), get_frame_time(), app.catch_up)
+                };
+                app.run_paced(due, frames, keypress);
             } else { // Step requested
                 app.chip8.tick(keypress);
+                app.tick_compare(keypress);
+                app.track_reg_highlights();
+                app.trace_instruction();
+                app.profile_instruction();
+                app.run_pc_hooks();
             }
             if app.chip8.redraw {
-                texture = pixels_to_texture2d(&app.chip8.display, &app.fg_color, &app.bg_color);
+                app.update_display_history();
+                let frame = app.chip8.frame();
+                // Only what changed is uploaded, unless the display changed size (or nothing says what changed)
+                let size = app.display_transform.size((frame.width, frame.height));
+                match frame.dirty {
+                    Some(dirty) if size == (texture.width() as usize, texture.height() as usize) => {
+                        update_texture_region(&texture, &frame, dirty, &app.fg_color, &app.bg_color, app.display_transform);
+                    }
+                    _ => texture = frame_to_texture2d(&frame, &app.fg_color, &app.bg_color, app.display_transform),
+                }
                 app.chip8.redraw = false;
+                app.chip8.take_dirty();
                 app.draw_per_sec += 1;
             }
             app.step = false;
         }
+        match &mut app.compare {
+            Some(compare) if compare.redraw || compare_texture.is_none() => {
+                compare_texture = Some(frame_to_texture2d(&compare.frame(), &app.fg_color, &app.bg_color, app.display_transform));
+                compare.redraw = false;
+            }
+            Some(_) => (),
+            None => compare_texture = None,
+        }
+        if app.pause_execution {
+            app.paused_frame(frames);
+        }
 
         // Render everything
         clear_background(BLACK);
-        set_camera(&Camera2D {
-            zoom: vec2(26.0 / screen_width(), 26.0 / screen_height()),
-            target: vec2(32., 16.),
-            ..Default::default()
-        });
-        draw_rectangle(-1., -1., 66., 34., GRAY);
-        draw_texture_ex(texture,
-                        0.0,
-                        0.0,
-                        WHITE,
-                        DrawTextureParams{
-                            dest_size: None,
-                            source: None,
-                            rotation: 0.0,
-                            flip_x: false,
-                            flip_y: true,
-                            pivot: None
-                        }
-        );
+        set_camera(&camera);
+        // A comparison machine is drawn to the right, each display with its own border
+        let borders = std::iter::once(app.border_color(&app.chip8)).chain(app.compare.as_ref().map(|compare| app.border_color(compare)));
+        let (area_width, area_height) = display_area(app.display_border);
+        let border_width = app.display_border as f32;
+        for (i, (texture, border)) in std::iter::once(texture).chain(compare_texture).zip(borders).enumerate() {
+            let left = i as f32 * area_width;
+            if app.display_border > 0 {
+                draw_rectangle(left - border_width, -border_width, area_width, area_height, border);
+            }
+            // High-res textures are squeezed into the same area, and rotated ones centred in it
+            let (width, height) = (texture.width() as usize, texture.height() as usize);
+            let (x, y, size) = app.display_transform.layout(app.display_transform.size((width, height)));
+            draw_texture_ex(texture,
+                            left + x,
+                            y,
+                            WHITE,
+                            DrawTextureParams{
+                                dest_size: Some(vec2(width as f32 * size, height as f32 * size)),
+                                source: None,
+                                rotation: 0.0,
+                                flip_x: false,
+                                flip_y: true,
+                                pivot: None
+                            }
+            );
+        }
+        app.update_display_diff();
+        app.draw_display_diff();
+        app.update_collisions();
+        app.draw_collisions();
+        app.draw_display_history();
+        app.draw_session_diff();
+        app.draw_sprite_preview();
         egui_macroquad::draw();
         next_frame().await
     }
 }
 
+// Parses a saved "WIDTHxHEIGHT" window size, keeping it within sizes a monitor could show
+fn parse_window_size(text: &str) -> Option<(i32, i32)> {
+    let (width, height) = text.split_once('x')?;
+    let width: i32 = width.trim().parse().ok()?;
+    let height: i32 = height.trim().parse().ok()?;
+    Some((width.clamp(320, 7680), height.clamp(200, 4320)))
+}
+
+// Width and height of the area drawn for each display, including a border this many low-res pixels wide
+fn display_area(border: u32) -> (f32, f32) {
+    (64. + 2. * border as f32, 32. + 2. * border as f32)
+}
+
+// Screen pixels per CHIP-8 pixel, rounded down to a whole number in pixel perfect mode so every pixel is the same size
+fn display_scale(scaling: DisplayScaling, screen_width: f32, screen_height: f32, displays: usize, border: u32) -> f32 {
+    let (area_width, area_height) = display_area(border);
+    let scale = (screen_width / (area_width * displays as f32)).min(screen_height / area_height);
+    match scaling {
+        DisplayScaling::Stretch => scale,
+        DisplayScaling::PixelPerfect => scale.floor().max(1.),
+    }
+}
+
+// Centres the displays in the window, keeping the top left corner on a whole screen pixel
+fn display_camera(scaling: DisplayScaling, displays: usize, border: u32) -> Camera2D {
+    let (width, height) = (screen_width(), screen_height());
+    let scale = display_scale(scaling, width, height, displays, border);
+    // Everything from the left edge of the first display to the right edge of the last
+    let content_width = display_area(border).0 * displays as f32 - 2. * border as f32;
+    let left = ((width - content_width * scale) / 2.).round();
+    let top = ((height - 32. * scale) / 2.).round();
+    Camera2D {
+        zoom: vec2(2. * scale / width, 2. * scale / height),
+        target: vec2((width / 2. - left) / scale, 32. - (height / 2. - top) / scale),
+        ..Default::default()
+    }
+}
+
+// Maps the mouse position through the display camera to a CHIP-8 pixel, if it's over the display
+fn pixel_under_mouse(camera: &Camera2D, dimensions: (usize, usize), transform: DisplayTransform) -> Option<(usize, usize)> {
+    let (mouse_x, mouse_y) = mouse_position();
+    let world = camera.screen_to_world(vec2(mouse_x, mouse_y));
+    let (left, bottom, size) = transform.layout(dimensions);
+    let (width, height) = transform.size(dimensions);
+    // The display is drawn flipped, so row 0 is at the top of world space
+    let (x, y) = (((world.x - left) / size).floor(), height as f32 - 1. - ((world.y - bottom) / size).floor());
+    if !(0. ..width as f32).contains(&x) || !(0. ..height as f32).contains(&y) {
+        return None;
+    }
+    Some(transform.invert((x as usize, y as usize), dimensions))
+}
+
 fn debug_label(ui: &mut Ui, title: &str, body: &str, color: Color32) {
     ui.horizontal(|ui| {
         ui.label(RichText::new(title).color(color));
@@ -112,20 +313,43 @@ fn debug_label(ui: &mut Ui, title: &str, body: &str, color: Color32) {
     });
 }
 
-fn pixels_to_texture2d(pixels: &[bool; 64 * 32], fg_color: &[f32;3], bg_color: &[f32;3]) -> Texture2D {
-    let mut bytes: Vec<u8> = Vec::from([0;8192]);
+// Builds the display texture already transformed, so it's drawn the right way round
+fn frame_to_texture2d(frame: &Frame, fg_color: &[f32;3], bg_color: &[f32;3], transform: DisplayTransform) -> Texture2D {
+    let pixels = frame.pixels();
+    let dimensions = (frame.width, frame.height);
+    let (width, height) = transform.size(dimensions);
+    let mut bytes: Vec<u8> = vec![0; pixels.len() * 4];
     for i in 0..pixels.len() {
-        let offset = i * 4;
-        bytes[offset + 0] = if pixels[i] { (fg_color[0] * 255.) as u8 } else { (bg_color[0] * 255.) as u8 };
-        bytes[offset + 1] = if pixels[i] { (fg_color[1] * 255.) as u8 } else { (bg_color[1] * 255.) as u8 };
-        bytes[offset + 2] = if pixels[i] { (fg_color[2] * 255.) as u8 } else { (bg_color[2] * 255.) as u8 };
-        bytes[offset + 3] = 255;
+        let (x, y) = transform.apply((i % frame.width, i / frame.width), dimensions);
+        let offset = (y * width + x) * 4;
+        bytes[offset..offset + 4].copy_from_slice(&pixel_rgba(pixels[i], fg_color, bg_color));
     }
-    let texture = Texture2D::from_rgba8(64, 32, &bytes);
+    let texture = Texture2D::from_rgba8(width as u16, height as u16, &bytes);
     texture.set_filter(FilterMode::Nearest);
     texture
 }
 
+// Uploads just the changed part of the display into a texture built by frame_to_texture2d
+fn update_texture_region(texture: &Texture2D, frame: &Frame, dirty: DirtyRect, fg_color: &[f32;3], bg_color: &[f32;3], transform: DisplayTransform) {
+    let dimensions = (frame.width, frame.height);
+    let region = transform.apply_rect(dirty, dimensions);
+    let mut bytes: Vec<u8> = vec![0; region.width * region.height * 4];
+    for y in dirty.y..dirty.y + dirty.height {
+        for x in dirty.x..dirty.x + dirty.width {
+            let (x_drawn, y_drawn) = transform.apply((x, y), dimensions);
+            let offset = ((y_drawn - region.y) * region.width + x_drawn - region.x) * 4;
+            bytes[offset..offset + 4].copy_from_slice(&pixel_rgba(frame.pixel(x, y), fg_color, bg_color));
+        }
+    }
+    let gl = unsafe { get_internal_gl() };
+    texture.raw_miniquad_texture_handle().update_texture_part(gl.quad_context, region.x as i32, region.y as i32, region.width as i32, region.height as i32, &bytes);
+}
+
+fn pixel_rgba(set: bool, fg_color: &[f32;3], bg_color: &[f32;3]) -> [u8; 4] {
+    let color = if set { fg_color } else { bg_color };
+    [(color[0] * 255.) as u8, (color[1] * 255.) as u8, (color[2] * 255.) as u8, 255]
+}
+
 fn setup_custom_fonts(ctx: &egui::Context) {
     // Start with the default fonts (we will be adding to them rather than replacing them).
     let mut fonts = egui::FontDefinitions::default();
@@ -153,4 +377,30 @@ fn setup_custom_fonts(ctx: &egui::Context) {
 
     // Tell egui to use these fonts:
     ctx.set_fonts(fonts);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_scale() {
+        assert_eq!(display_scale(DisplayScaling::Stretch, 990., 600., 1, 1), 15.);
+        assert_eq!(display_scale(DisplayScaling::PixelPerfect, 960., 600., 1, 1), 14.);
+        assert_eq!(display_scale(DisplayScaling::PixelPerfect, 960., 300., 1, 1), 8.);
+        assert_eq!(display_scale(DisplayScaling::PixelPerfect, 40., 20., 1, 1), 1.);
+        // No border leaves more room for the display, a thick one less
+        assert_eq!(display_scale(DisplayScaling::PixelPerfect, 960., 600., 1, 0), 15.);
+        assert_eq!(display_scale(DisplayScaling::Stretch, 960., 600., 1, 8), 12.);
+        // Two displays side by side share the width
+        assert_eq!(display_scale(DisplayScaling::PixelPerfect, 960., 600., 2, 1), 7.);
+    }
+
+    #[test]
+    fn test_parse_window_size() {
+        assert_eq!(parse_window_size("1280x720"), Some((1280, 720)));
+        assert_eq!(parse_window_size("10x100000"), Some((320, 4320)));
+        assert_eq!(parse_window_size("1280"), None);
+        assert_eq!(parse_window_size("wide x tall"), None);
+    }
+}
@@ -0,0 +1,338 @@
+use std::ops::Range;
+
+pub struct OpcodeInfo {
+    pub pattern: &'static str,
+    pub mask: u16,
+    pub value: u16,
+    pub description: &'static str,
+}
+
+const fn op(pattern: &'static str, mask: u16, value: u16, description: &'static str) -> OpcodeInfo {
+    OpcodeInfo { pattern, mask, value, description }
+}
+
+// Opcodes the interpreter doesn't implement yet. Remove an entry (and add a test) when implementing it,
+// test_opcode_coverage fails if one of them runs.
+pub const UNIMPLEMENTED: [&str; 0] = [];
+
+impl OpcodeInfo {
+    pub fn implemented(&self) -> bool {
+        !UNIMPLEMENTED.contains(&self.pattern)
+    }
+}
+
+// Whether an opcode is unknown or one the interpreter doesn't implement yet
+pub fn is_unimplemented(code: u16) -> bool {
+    lookup(code).is_none_or(|info| !info.implemented())
+}
+
+// Checked in order, so the specific 00xx forms come before 0NNN
+pub const OPCODES: [OpcodeInfo; 40] = [
+    op("00CN", 0xFFF0, 0x00C0, "Scrolls the display down by N pixels. (SUPER-CHIP)"),
+    op("00E0", 0xFFFF, 0x00E0, "Clears the screen."),
+    op("00EE", 0xFFFF, 0x00EE, "Returns from a subroutine."),
+    op("00FB", 0xFFFF, 0x00FB, "Scrolls the display right by the horizontal scroll quirk (4 pixels by default), halved in low-res with the half scroll quirk. (SUPER-CHIP)"),
+    op("00FC", 0xFFFF, 0x00FC, "Scrolls the display left by the horizontal scroll quirk (4 pixels by default), halved in low-res with the half scroll quirk. (SUPER-CHIP)"),
+    op("00FE", 0xFFFF, 0x00FE, "Switches to the 64x32 low-res display. (SUPER-CHIP)"),
+    op("00FF", 0xFFFF, 0x00FF, "Switches to the 128x64 high-res display. (SUPER-CHIP)"),
+    op("0NNN", 0xF000, 0x0000, "Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs."),
+    op("1NNN", 0xF000, 0x1000, "Jumps to address NNN."),
+    op("2NNN", 0xF000, 0x2000, "Calls subroutine at NNN."),
+    op("3XNN", 0xF000, 0x3000, "Skips the next instruction if VX equals NN. (Usually the next instruction is a jump to skip a code block)"),
+    op("4XNN", 0xF000, 0x4000, "Skips the next instruction if VX does not equal NN. (Usually the next instruction is a jump to skip a code block)"),
+    op("5XY0", 0xF00F, 0x5000, "Skips the next instruction if VX equals VY. (Usually the next instruction is a jump to skip a code block)"),
+    op("6XNN", 0xF000, 0x6000, "Sets VX to NN."),
+    op("7XNN", 0xF000, 0x7000, "Adds NN to VX. (Carry flag is not changed)"),
+    op("8XY0", 0xF00F, 0x8000, "Sets VX to the value of VY."),
+    op("8XY1", 0xF00F, 0x8001, "Sets VX to VX or VY. (Bitwise OR operation)"),
+    op("8XY2", 0xF00F, 0x8002, "Sets VX to VX and VY. (Bitwise AND operation)"),
+    op("8XY3", 0xF00F, 0x8003, "Sets VX to VX xor VY."),
+    op("8XY4", 0xF00F, 0x8004, "Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not."),
+    op("8XY5", 0xF00F, 0x8005, "VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there is not."),
+    op("8XY6", 0xF00F, 0x8006, "Shifts VY to the right by 1 into VX, or VX itself with the shift quirk. VF is set to the bit shifted out."),
+    op("8XY7", 0xF00F, 0x8007, "Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there is not."),
+    op("8XYE", 0xF00F, 0x800E, "Shifts VY to the left by 1 into VX, or VX itself with the shift quirk. VF is set to the bit shifted out."),
+    op("9XY0", 0xF00F, 0x9000, "Skips the next instruction if VX does not equal VY. (Usually the next instruction is a jump to skip a code block)"),
+    op("ANNN", 0xF000, 0xA000, "Sets I to the address NNN."),
+    op("BNNN", 0xF000, 0xB000, "Jumps to the address NNN plus V0, or plus VX with the jump quirk, where X is the top digit of NNN."),
+    op("CXNN", 0xF000, 0xC000, "Sets VX to the result of a bitwise and operation on a random number (Typically: 0 to 255) and NN."),
+    op("DXYN", 0xF000, 0xD000, "Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels. Each row of 8 pixels is read as bit-coded starting from memory location I; I value does not change after the execution of this instruction. As described above, VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen. In high-res with the clipped rows quirk, VF is instead the number of rows that collided or were clipped at the bottom edge."),
+    op("EX9E", 0xF0FF, 0xE09E, "Skips the next instruction if the key stored in VX is pressed. (Usually the next instruction is a jump to skip a code block)"),
+    op("EXA1", 0xF0FF, 0xE0A1, "Skips the next instruction if the key stored in VX is not pressed. (Usually the next instruction is a jump to skip a code block)"),
+    op("FX07", 0xF0FF, 0xF007, "Sets VX to the value of the delay timer."),
+    op("FX0A", 0xF0FF, 0xF00A, "A key press is awaited, and then stored in VX. (Blocking Operation. All instruction halted until next key event)"),
+    op("FX15", 0xF0FF, 0xF015, "Sets the delay timer to VX."),
+    op("FX18", 0xF0FF, 0xF018, "Sets the sound timer to VX."),
+    op("FX1E", 0xF0FF, 0xF01E, "Adds VX to I. VF is not affected, unless the index overflow quirk sets it to 1 when I goes past 0xFFF and to 0 when it doesn't."),
+    op("FX29", 0xF0FF, 0xF029, "Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font."),
+    op("FX33", 0xF0FF, 0xF033, "Stores the binary-coded decimal representation of VX, with the most significant of three digits at the address in I, the middle digit at I plus 1, and the least significant digit at I plus 2. (In other words, take the decimal representation of VX, place the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.)"),
+    op("FX55", 0xF0FF, 0xF055, "Stores from V0 to VX (including VX) in memory, starting at address I. I then moves on by X + 1, by X, or not at all, depending on the load/store quirk."),
+    op("FX65", 0xF0FF, 0xF065, "Fills from V0 to VX (including VX) with values from memory, starting at address I. I then moves on by X + 1, by X, or not at all, depending on the load/store quirk."),
+];
+
+pub fn lookup(code: u16) -> Option<&'static OpcodeInfo> {
+    OPCODES.iter().find(|info| code & info.mask == info.value)
+}
+
+// Where an opcode is in OPCODES, which is also its bit in Chip8::enabled_opcodes
+pub fn index(code: u16) -> Option<usize> {
+    OPCODES.iter().position(|info| code & info.mask == info.value)
+}
+
+pub fn describe(code: u16) -> (&'static str, &'static str) {
+    match lookup(code) {
+        Some(info) => (info.pattern, info.description),
+        None => ("????", "Unknown opcode."),
+    }
+}
+
+/// Formats an opcode using the common Cowgod-style mnemonics
+pub fn disassemble(code: u16) -> String {
+    let nnn = code & 0x0FFF;
+    let nn = code & 0x00FF;
+    let n = code & 0x000F;
+    let x = (code & 0x0F00) >> 8;
+    let y = (code & 0x00F0) >> 4;
+    let pattern = match lookup(code) {
+        Some(info) => info.pattern,
+        None => return format!("DW {:#06X}", code),
+    };
+    match pattern {
+        "00CN" => format!("SCD {}", n),
+        "00E0" => "CLS".to_owned(),
+        "00EE" => "RET".to_owned(),
+        "00FB" => "SCR".to_owned(),
+        "00FC" => "SCL".to_owned(),
+        "00FE" => "LOW".to_owned(),
+        "00FF" => "HIGH".to_owned(),
+        "0NNN" => format!("SYS {:#05X}", nnn),
+        "1NNN" => format!("JP {:#05X}", nnn),
+        "2NNN" => format!("CALL {:#05X}", nnn),
+        "3XNN" => format!("SE V{:X}, {:#04X}", x, nn),
+        "4XNN" => format!("SNE V{:X}, {:#04X}", x, nn),
+        "5XY0" => format!("SE V{:X}, V{:X}", x, y),
+        "6XNN" => format!("LD V{:X}, {:#04X}", x, nn),
+        "7XNN" => format!("ADD V{:X}, {:#04X}", x, nn),
+        "8XY0" => format!("LD V{:X}, V{:X}", x, y),
+        "8XY1" => format!("OR V{:X}, V{:X}", x, y),
+        "8XY2" => format!("AND V{:X}, V{:X}", x, y),
+        "8XY3" => format!("XOR V{:X}, V{:X}", x, y),
+        "8XY4" => format!("ADD V{:X}, V{:X}", x, y),
+        "8XY5" => format!("SUB V{:X}, V{:X}", x, y),
+        "8XY6" => format!("SHR V{:X}, V{:X}", x, y),
+        "8XY7" => format!("SUBN V{:X}, V{:X}", x, y),
+        "8XYE" => format!("SHL V{:X}, V{:X}", x, y),
+        "9XY0" => format!("SNE V{:X}, V{:X}", x, y),
+        "ANNN" => format!("LD I, {:#05X}", nnn),
+        "BNNN" => format!("JP V0, {:#05X}", nnn),
+        "CXNN" => format!("RND V{:X}, {:#04X}", x, nn),
+        "DXYN" => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        "EX9E" => format!("SKP V{:X}", x),
+        "EXA1" => format!("SKNP V{:X}", x),
+        "FX07" => format!("LD V{:X}, DT", x),
+        "FX0A" => format!("LD V{:X}, K", x),
+        "FX15" => format!("LD DT, V{:X}", x),
+        "FX18" => format!("LD ST, V{:X}", x),
+        "FX1E" => format!("ADD I, V{:X}", x),
+        "FX29" => format!("LD F, V{:X}", x),
+        "FX33" => format!("LD B, V{:X}", x),
+        "FX55" => format!("LD [I], V{:X}", x),
+        "FX65" => format!("LD V{:X}, [I]", x),
+        _ => unreachable!(),
+    }
+}
+
+// Addresses that 1NNN, 2NNN and BNNN can jump to, which get labels in the listing
+fn branch_target(code: u16) -> Option<usize> {
+    match code & 0xF000 {
+        0x1000 | 0x2000 | 0xB000 => Some((code & 0x0FFF) as usize),
+        _ => None,
+    }
+}
+
+fn label(address: usize) -> String {
+    format!("label_0x{:03X}", address)
+}
+
+// Parses a program written as hex opcodes, like "00E0 A22A 600C", for loading with load_vec
+pub fn parse_hex_program(text: &str) -> Result<Vec<u16>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+            match u16::from_str_radix(digits, 16) {
+                Ok(code) if digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()) => Ok(code),
+                _ => Err(format!("'{}' isn't a 4 digit hex opcode", token)),
+            }
+        })
+        .collect()
+}
+
+pub fn is_data(data: &[Range<usize>], address: usize) -> bool {
+    data.iter().any(|range| range.contains(&address))
+}
+
+// Marks addresses as data, or back to code, keeping the ranges sorted and without overlaps
+pub fn mark_data(data: &mut Vec<Range<usize>>, range: Range<usize>, is_data: bool) {
+    let mut marked: Vec<Range<usize>> = Vec::new();
+    for existing in data.drain(..) {
+        // Keep whatever sticks out either side of the range being marked
+        if existing.start < range.start {
+            marked.push(existing.start..existing.end.min(range.start));
+        }
+        if existing.end > range.end {
+            marked.push(existing.start.max(range.end)..existing.end);
+        }
+    }
+    if is_data && !range.is_empty() {
+        marked.push(range);
+    }
+    marked.sort_by_key(|range| range.start);
+    // Join ranges that touch
+    for range in marked {
+        match data.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => data.push(range),
+        }
+    }
+}
+
+// Data ranges as text, like "20A-210 300-340", for saving in the config
+pub fn format_data_ranges(data: &[Range<usize>]) -> String {
+    data.iter().map(|range| format!("{:03X}-{:03X}", range.start, range.end)).collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse_data_ranges(text: &str) -> Vec<Range<usize>> {
+    text.split_whitespace()
+        .filter_map(|range| {
+            let (start, end) = range.split_once('-')?;
+            Some(usize::from_str_radix(start, 16).ok()?..usize::from_str_radix(end, 16).ok()?)
+        })
+        .collect()
+}
+
+/// Writes an annotated listing of a ROM loaded at `start`, with labels on every jump and call target.
+/// Words in the `data` address ranges are written as bytes rather than disassembled.
+pub fn listing(rom: &[u8], start: usize, data: &[Range<usize>]) -> String {
+    let words: Vec<(usize, &[u8])> = rom.chunks(2).enumerate().map(|(i, bytes)| (start + i * 2, bytes)).collect();
+    // First pass finds the branch targets inside the ROM, the second writes them out as labels
+    let targets: Vec<usize> = words
+        .iter()
+        .filter(|(address, bytes)| bytes.len() == 2 && !is_data(data, *address))
+        .filter_map(|(_, bytes)| branch_target(u16::from_be_bytes([bytes[0], bytes[1]])))
+        .filter(|target| (start..start + rom.len()).contains(target))
+        .collect();
+    let mut lines = Vec::new();
+    for (address, bytes) in words {
+        if targets.contains(&address) {
+            lines.push(format!("{}:", label(address)));
+        }
+        if bytes.len() < 2 {
+            lines.push(format!("{:03X}: {:02X}     DB {:#04X}", address, bytes[0], bytes[0]));
+            continue;
+        }
+        if is_data(data, address) {
+            lines.push(format!("{:03X}: {:02X} {:02X}  DB {:#04X}, {:#04X}", address, bytes[0], bytes[1], bytes[0], bytes[1]));
+            continue;
+        }
+        let code = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let mut mnemonic = disassemble(code);
+        if let Some(target) = branch_target(code).filter(|target| targets.contains(target)) {
+            mnemonic = mnemonic.replace(&format!("{:#05X}", target), &label(target));
+        }
+        let (_, description) = describe(code);
+        lines.push(format!("{:03X}: {:02X} {:02X}  {:<20} ; {}", address, bytes[0], bytes[1], mnemonic, description));
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implemented_count() {
+        assert_eq!(OPCODES.iter().filter(|info| info.implemented()).count(), 40);
+        // Every unimplemented entry has to name a real opcode, or it would never be removed
+        for pattern in UNIMPLEMENTED {
+            assert!(OPCODES.iter().any(|info| info.pattern == pattern), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_is_unimplemented() {
+        assert!(is_unimplemented(0x5121)); // No such opcode
+        assert!(!is_unimplemented(0xD125));
+    }
+
+    #[test]
+    fn test_lookup_description() {
+        let info = lookup(0x8AB4).unwrap();
+        assert_eq!(info.pattern, "8XY4");
+        assert_eq!(info.description, "Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not.");
+        assert_eq!(lookup(0x00E0).unwrap().pattern, "00E0");
+        assert_eq!(lookup(0x0208).unwrap().pattern, "0NNN");
+        assert!(lookup(0x8AB8).is_none());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(disassemble(0x8AB4), "ADD VA, VB");
+        assert_eq!(disassemble(0xD123), "DRW V1, V2, 3");
+        assert_eq!(disassemble(0xA22A), "LD I, 0x22A");
+        assert_eq!(disassemble(0xFFFF), "DW 0xFFFF");
+    }
+
+    #[test]
+    fn test_listing_labels_jump_targets() {
+        let rom = [0x60, 0x05, 0x22, 0x06, 0x12, 0x04, 0x00, 0xEE, 0xFF];
+        let listing = listing(&rom, 0x200, &[]);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert!(lines[0].starts_with("200: 60 05  LD V0, 0x05"));
+        assert!(lines[1].starts_with("202: 22 06  CALL label_0x206"));
+        assert_eq!(lines[2], "label_0x204:");
+        assert!(lines[3].starts_with("204: 12 04  JP label_0x204"));
+        assert!(lines[3].ends_with("; Jumps to address NNN."));
+        assert_eq!(lines[4], "label_0x206:");
+        assert!(lines[5].starts_with("206: 00 EE  RET"));
+        assert_eq!(lines[6], "208: FF     DB 0xFF");
+    }
+
+    #[test]
+    fn test_listing_data_ranges() {
+        // A jump over a sprite, which shouldn't be disassembled or have its bytes read as a jump target
+        let rom = [0x12, 0x06, 0xF0, 0x90, 0x12, 0x00, 0x00, 0xE0];
+        let mut data = Vec::new();
+        mark_data(&mut data, 0x202..0x206, true);
+        let listing = listing(&rom, 0x200, &data);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert!(lines[0].starts_with("200: 12 06  JP label_0x206"));
+        assert_eq!(lines[1], "202: F0 90  DB 0xF0, 0x90");
+        assert_eq!(lines[2], "204: 12 00  DB 0x12, 0x00");
+        assert_eq!(lines[3], "label_0x206:");
+        assert!(lines[4].starts_with("206: 00 E0  CLS"));
+    }
+
+    #[test]
+    fn test_parse_hex_program() {
+        assert_eq!(parse_hex_program("00E0 a22a\n0x600C, 1206"), Ok(vec![0x00E0, 0xA22A, 0x600C, 0x1206]));
+        assert_eq!(parse_hex_program(""), Ok(vec![]));
+        assert!(parse_hex_program("00E0 A22").is_err());
+        assert!(parse_hex_program("00E0 G000").is_err());
+        assert!(parse_hex_program("+123").is_err());
+        assert!(parse_hex_program("0x0x1234").is_err());
+    }
+
+    #[test]
+    fn test_mark_data() {
+        let mut data = Vec::new();
+        mark_data(&mut data, 0x200..0x210, true);
+        mark_data(&mut data, 0x210..0x220, true);
+        assert_eq!(data, vec![0x200..0x220]);
+        mark_data(&mut data, 0x208..0x20C, false);
+        assert_eq!(data, vec![0x200..0x208, 0x20C..0x220]);
+        assert_eq!(parse_data_ranges(&format_data_ranges(&data)), data);
+        mark_data(&mut data, 0x000..0x1000, false);
+        assert!(data.is_empty());
+    }
+}
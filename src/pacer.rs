@@ -0,0 +1,125 @@
+use crate::chip8::quirks::Profile;
+
+// Never run more than a tenth of a second of instructions at once, so a stalled frame doesn't snowball
+const MAX_CATCH_UP: f32 = 0.1;
+// In catch-up mode time lost to a stall is made up, but a long pause still shouldn't come back as one huge burst
+const MAX_CATCH_UP_MODE: f32 = 2.;
+
+// How many seconds behind the wall clock can be made up at once
+fn catch_up_limit(catch_up: bool) -> f32 {
+    if catch_up { MAX_CATCH_UP_MODE } else { MAX_CATCH_UP }
+}
+
+/// Spreads a target instruction rate over frames of any length, carrying the fractions between frames
+pub struct Pacer {
+    carry: f32,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Pacer { carry: 0. }
+    }
+
+    // With `catch_up` a slow frame is followed by the instructions it missed, up to a couple of seconds' worth
+    pub fn instructions_due(&mut self, ips: u32, elapsed: f32, catch_up: bool) -> u32 {
+        let due = ips as f32 * elapsed.min(catch_up_limit(catch_up)) + self.carry;
+        self.carry = due.fract();
+        due as u32
+    }
+}
+
+/// Counts whole 60Hz frames against the wall clock, however often the screen refreshes
+pub struct FrameClock {
+    start: f64,
+    frames: u64,
+}
+
+impl FrameClock {
+    pub fn new(time: f64) -> Self {
+        FrameClock { start: time, frames: 0 }
+    }
+
+    // Frames that should have run by `time` (in seconds) and haven't yet. Working from the total
+    // rather than adding up frame times means rounding errors can't build up.
+    pub fn frames_due(&mut self, time: f64, catch_up: bool) -> u32 {
+        let total = ((time - self.start) * 60. + 1e-6).floor().max(0.) as u64;
+        // Anything past the catch-up limit is dropped rather than run late
+        let due = total.saturating_sub(self.frames).min((catch_up_limit(catch_up) * 60.) as u64);
+        self.frames = total;
+        due as u32
+    }
+}
+
+/// A machine CHIP-8 ran on: its quirks and roughly how fast it ran programs
+pub struct Platform {
+    pub name: &'static str,
+    pub profile: Profile,
+    pub ips: u32,
+}
+
+const fn platform(name: &'static str, profile: Profile, ips: u32) -> Platform {
+    Platform { name, profile, ips }
+}
+
+// The 1802 based machines all ran at around 1.76MHz, which works out at roughly 9 instructions a frame.
+// The later targets' speeds are the usual figures emulators pick rather than anything measured.
+pub const PLATFORMS: [Platform; 6] = [
+    platform("COSMAC VIP", Profile::CosmacVip, 540),
+    platform("Telmac 1800", Profile::CosmacVip, 540),
+    platform("ETI-660", Profile::CosmacVip, 540),
+    platform("HP 48 (CHIP-48)", Profile::Chip48, 1800),
+    platform("HP 48 (SUPER-CHIP)", Profile::SuperChip, 1800),
+    platform("XO-CHIP", Profile::XoChip, 60000),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer_carries_fractions() {
+        let mut pacer = Pacer::new();
+        // 100 IPS at 144Hz is 0.69 instructions a frame
+        let total: u32 = (0..144).map(|_| pacer.instructions_due(100, 1. / 144., false)).sum();
+        assert!((99..=100).contains(&total));
+        assert_eq!(pacer.instructions_due(540, 1. / 60., false), 9);
+    }
+
+    #[test]
+    fn test_frame_clock_ticks_exactly_60hz() {
+        for refresh_rate in [30, 59, 60, 75, 144, 240] {
+            let mut clock = FrameClock::new(10.);
+            let seconds = 5;
+            let ticks: u32 = (1..=refresh_rate * seconds)
+                .map(|frame| clock.frames_due(10. + frame as f64 / refresh_rate as f64, false))
+                .sum();
+            assert_eq!(ticks, 60 * seconds as u32, "{}Hz", refresh_rate);
+        }
+    }
+
+    #[test]
+    fn test_frame_clock_skips_long_stalls() {
+        let mut clock = FrameClock::new(0.);
+        assert_eq!(clock.frames_due(10., false), 6);
+        assert_eq!(clock.frames_due(10. + 1. / 60., false), 1);
+    }
+
+    #[test]
+    fn test_pacer_limits_catch_up() {
+        let mut pacer = Pacer::new();
+        assert_eq!(pacer.instructions_due(600, 5., false), 60);
+    }
+
+    #[test]
+    fn test_catch_up_makes_up_lost_time() {
+        let mut pacer = Pacer::new();
+        // Half a second stalled at 600 IPS comes back in full, a long pause only up to the cap
+        assert_eq!(pacer.instructions_due(600, 0.5, true), 300);
+        assert_eq!(pacer.instructions_due(600, 30., true), 1200);
+
+        let mut clock = FrameClock::new(0.);
+        assert_eq!(clock.frames_due(1., true), 60);
+        assert_eq!(clock.frames_due(1. + 1. / 60., true), 1);
+        assert_eq!(clock.frames_due(60., true), 120);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use log::info;
+use crate::chip8::quirks::Quirks;
+use crate::config;
+use crate::input::KeypadLayout;
+
+const HEADER: &str = "CHIP8-PROFILE 1";
+const MAX_SPEED: u32 = 1000;
+
+/// A named set of settings that can be shared as a file, in the same `key = value` format as the config
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingsProfile {
+    pub name: String,
+    pub quirks: Quirks,
+    pub keypad_layout: KeypadLayout,
+    pub fg_color: [f32; 3],
+    pub bg_color: [f32; 3],
+    pub speed: u32,
+}
+
+impl SettingsProfile {
+    pub fn save(&self, filename: &str) -> std::io::Result<()> {
+        fs::write(filename, self.to_text())?;
+        info!("Exported profile '{}' to '{}'", self.name, filename);
+        Ok(())
+    }
+
+    pub fn load(filename: &str) -> Result<SettingsProfile, String> {
+        let text = fs::read_to_string(filename).map_err(|e| e.to_string())?;
+        SettingsProfile::parse(&text)
+    }
+
+    pub fn to_text(&self) -> String {
+        [
+            format!("# {}", HEADER),
+            format!("name = {}", self.name),
+            format!("quirks = {}", self.quirks.to_text()),
+            format!("keypad_layout = {}", self.keypad_layout.name()),
+            format!("fg_color = {}", color_to_text(&self.fg_color)),
+            format!("bg_color = {}", color_to_text(&self.bg_color)),
+            format!("speed = {}", self.speed),
+        ]
+        .join("\n") + "\n"
+    }
+
+    // Every known setting has to be present and valid, unknown ones are ignored for newer files
+    pub fn parse(text: &str) -> Result<SettingsProfile, String> {
+        if text.lines().next().map(str::trim) != Some(&format!("# {}", HEADER)) {
+            return Err("Not a settings profile".to_owned());
+        }
+        let values = config::parse(text);
+        let value = |key: &str| values.get(key).map(String::as_str).ok_or(format!("Missing '{}'", key));
+        let speed = value("speed")?.parse().map_err(|_| "Bad speed".to_owned())?;
+        if !(1..=MAX_SPEED).contains(&speed) {
+            return Err(format!("Speed must be between 1 and {}", MAX_SPEED));
+        }
+        Ok(SettingsProfile {
+            name: value("name")?.to_owned(),
+            quirks: Quirks::parse_text(value("quirks")?)?,
+            keypad_layout: KeypadLayout::from_name(value("keypad_layout")?).ok_or("Bad keypad layout")?,
+            fg_color: parse_color(value("fg_color")?)?,
+            bg_color: parse_color(value("bg_color")?)?,
+            speed,
+        })
+    }
+}
+
+fn color_to_text(color: &[f32; 3]) -> String {
+    color.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn parse_color(text: &str) -> Result<[f32; 3], String> {
+    let channels: Vec<f32> = text.split(',').map(|c| c.trim().parse()).collect::<Result<_, _>>()
+        .map_err(|_| format!("Bad colour '{}'", text))?;
+    match channels[..] {
+        [r, g, b] if channels.iter().all(|c| (0. ..=1.).contains(c)) => Ok([r, g, b]),
+        _ => Err(format!("Bad colour '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::quirks::Profile;
+
+    #[test]
+    fn test_profile_round_trip() {
+        let profile = SettingsProfile {
+            name: "Space Invaders".to_owned(),
+            quirks: Quirks::from_profile(Profile::SuperChip),
+            keypad_layout: KeypadLayout::Numpad,
+            fg_color: [0.2, 1., 0.25],
+            bg_color: [0., 0., 0.1],
+            speed: 15,
+        };
+        let filename = std::env::temp_dir().join("chip8_test_profile.cfg");
+        let filename = filename.to_str().unwrap();
+        profile.save(filename).unwrap();
+        assert_eq!(SettingsProfile::load(filename).unwrap(), profile);
+        fs::remove_file(filename).unwrap();
+
+        // Settings from a newer version are skipped
+        let text = profile.to_text() + "palette = amber\n";
+        assert_eq!(SettingsProfile::parse(&text).unwrap(), profile);
+    }
+
+    #[test]
+    fn test_profile_validation() {
+        let text = SettingsProfile {
+            name: "Test".to_owned(),
+            quirks: Quirks::default(),
+            keypad_layout: KeypadLayout::Qwerty,
+            fg_color: [1.; 3],
+            bg_color: [0.; 3],
+            speed: 6,
+        }.to_text();
+        assert!(SettingsProfile::parse(&text.replace("speed = 6", "speed = 0")).is_err());
+        assert!(SettingsProfile::parse(&text.replace("fg_color = 1,1,1", "fg_color = 1,2,1")).is_err());
+        assert!(SettingsProfile::parse(&text.replace("fg_color = 1,1,1", "fg_color = 1,1")).is_err());
+        assert!(SettingsProfile::parse(&text.replace("QWERTY", "Dvorak")).is_err());
+        assert!(SettingsProfile::parse(&text.replace("name = Test\n", "")).is_err());
+        assert!(SettingsProfile::parse("speed = 6\n").is_err());
+    }
+}
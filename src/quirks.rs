@@ -0,0 +1,237 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    CosmacVip,
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 4] = [Profile::CosmacVip, Profile::Chip48, Profile::SuperChip, Profile::XoChip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::CosmacVip => "COSMAC VIP",
+            Profile::Chip48 => "CHIP-48",
+            Profile::SuperChip => "SUPER-CHIP",
+            Profile::XoChip => "XO-CHIP",
+        }
+    }
+}
+
+// How far FX55/FX65 move I after storing or loading registers V0 to VX
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadStore {
+    // I ends up past the last register, by x + 1 (COSMAC VIP, XO-CHIP)
+    Increment,
+    // I moves by x, one short of the last register (CHIP-48)
+    IncrementMinusOne,
+    // I is left alone (SUPER-CHIP)
+    None,
+}
+
+impl LoadStore {
+    pub const ALL: [LoadStore; 3] = [LoadStore::Increment, LoadStore::IncrementMinusOne, LoadStore::None];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoadStore::Increment => "I += X + 1",
+            LoadStore::IncrementMinusOne => "I += X",
+            LoadStore::None => "I unchanged",
+        }
+    }
+
+    pub fn increment(&self, x: usize) -> u16 {
+        match self {
+            LoadStore::Increment => x as u16 + 1,
+            LoadStore::IncrementMinusOne => x as u16,
+            LoadStore::None => 0,
+        }
+    }
+}
+
+pub const MAX_HORIZONTAL_SCROLL: usize = 8;
+
+/// Behaviours that differ between CHIP-8 interpreters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    // I addresses the full 16 bits (XO-CHIP) rather than wrapping at 12 bits.
+    // Memory is still 4K, so addresses past it read as 0 and ignore writes.
+    pub wide_index: bool,
+    // Whether sprites wrap round each edge of the screen or are clipped by it. A pixel only
+    // wraps if the edge it leaves through and the opposite edge it comes back in through both wrap.
+    pub wrap_left: bool,
+    pub wrap_right: bool,
+    pub wrap_top: bool,
+    pub wrap_bottom: bool,
+    // 8XY1/8XY2/8XY3 clear VF afterwards, as on the COSMAC VIP
+    pub vf_reset: bool,
+    // Scrolling in low-res moves half as far, as on the original SUPER-CHIP 1.1
+    pub half_scroll_lowres: bool,
+    // In high-res DXYN sets VF to the number of rows that collided or were clipped at the
+    // bottom edge, as SUPER-CHIP 1.1 does, rather than just 0 or 1
+    pub vf_clipped_rows: bool,
+    // FX18 treats values below 2 as 0, which the COSMAC VIP couldn't make a sound for.
+    // Off in every profile since most ROMs don't rely on it.
+    pub sound_floor: bool,
+    pub load_store: LoadStore,
+    // EX9E/EXA1 only see one key at a time, the lowest one held, as interpreters that read a single
+    // key code did. Off in every profile, where every held key counts.
+    pub single_key: bool,
+    // How many pixels 00FB/00FC scroll by, before half_scroll_lowres. 4 everywhere by convention.
+    pub horizontal_scroll: usize,
+    // Non-standard: FX0A doesn't wait when no key is down, it stores the last key pressed (if any)
+    // and carries on. A few ROMs written against interpreters that did this need it.
+    pub fx0a_polling: bool,
+    // 8XY6/8XYE shift VX in place and ignore VY (CHIP-48, SUPER-CHIP), rather than setting VX to VY shifted
+    pub shift_vx: bool,
+    // BNNN jumps to NNN plus VX, where X is the top digit of NNN, rather than plus V0 (CHIP-48, SUPER-CHIP)
+    pub jump_vx: bool,
+    // FX1E sets VF to 1 when I goes past 0xFFF and to 0 when it doesn't, as the Amiga interpreter did.
+    // Off in every profile, but a few games rely on it.
+    pub index_overflow_vf: bool,
+}
+
+impl Quirks {
+    pub fn from_profile(profile: Profile) -> Self {
+        let xo_chip = profile == Profile::XoChip;
+        Quirks {
+            wide_index: xo_chip,
+            wrap_left: xo_chip,
+            wrap_right: xo_chip,
+            wrap_top: xo_chip,
+            wrap_bottom: xo_chip,
+            vf_reset: profile == Profile::CosmacVip,
+            half_scroll_lowres: false,
+            vf_clipped_rows: profile == Profile::SuperChip,
+            sound_floor: false,
+            load_store: match profile {
+                Profile::CosmacVip | Profile::XoChip => LoadStore::Increment,
+                Profile::Chip48 => LoadStore::IncrementMinusOne,
+                Profile::SuperChip => LoadStore::None,
+            },
+            single_key: false,
+            horizontal_scroll: 4,
+            fx0a_polling: false,
+            shift_vx: matches!(profile, Profile::Chip48 | Profile::SuperChip),
+            jump_vx: matches!(profile, Profile::Chip48 | Profile::SuperChip),
+            index_overflow_vf: false,
+        }
+    }
+
+    // The profile these settings match, if they haven't been customised
+    pub fn profile(&self) -> Option<Profile> {
+        Profile::ALL.into_iter().find(|profile| Quirks::from_profile(*profile) == *self)
+    }
+
+    pub fn profile_name(&self) -> &'static str {
+        self.profile().map(|profile| profile.name()).unwrap_or("Custom")
+    }
+
+    pub fn wraps_horizontally(&self) -> bool {
+        self.wrap_left && self.wrap_right
+    }
+
+    pub fn wraps_vertically(&self) -> bool {
+        self.wrap_top && self.wrap_bottom
+    }
+
+    pub fn to_text(self) -> String {
+        format!(
+            "wide_index={} wrap_left={} wrap_right={} wrap_top={} wrap_bottom={} vf_reset={} half_scroll_lowres={} vf_clipped_rows={} sound_floor={} load_store={} single_key={} horizontal_scroll={} fx0a_polling={} shift_vx={} jump_vx={} index_overflow_vf={}",
+            self.wide_index as u8,
+            self.wrap_left as u8,
+            self.wrap_right as u8,
+            self.wrap_top as u8,
+            self.wrap_bottom as u8,
+            self.vf_reset as u8,
+            self.half_scroll_lowres as u8,
+            self.vf_clipped_rows as u8,
+            self.sound_floor as u8,
+            LoadStore::ALL.iter().position(|load_store| *load_store == self.load_store).unwrap(),
+            self.single_key as u8,
+            self.horizontal_scroll,
+            self.fx0a_polling as u8,
+            self.shift_vx as u8,
+            self.jump_vx as u8,
+            self.index_overflow_vf as u8
+        )
+    }
+
+    // Parses the output of to_text, ignoring unknown quirks so older builds can read newer files
+    pub fn parse_text(text: &str) -> Result<Quirks, String> {
+        let mut quirks = Quirks::default();
+        for field in text.split_whitespace() {
+            let (name, value) = field.split_once('=').ok_or(format!("Bad quirk '{}'", field))?;
+            let flag = || match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("Bad value for quirk '{}'", name)),
+            };
+            match name {
+                "wide_index" => quirks.wide_index = flag()?,
+                "wrap_left" => quirks.wrap_left = flag()?,
+                "wrap_right" => quirks.wrap_right = flag()?,
+                "wrap_top" => quirks.wrap_top = flag()?,
+                "wrap_bottom" => quirks.wrap_bottom = flag()?,
+                "vf_reset" => quirks.vf_reset = flag()?,
+                "half_scroll_lowres" => quirks.half_scroll_lowres = flag()?,
+                "vf_clipped_rows" => quirks.vf_clipped_rows = flag()?,
+                "sound_floor" => quirks.sound_floor = flag()?,
+                "single_key" => quirks.single_key = flag()?,
+                "fx0a_polling" => quirks.fx0a_polling = flag()?,
+                "shift_vx" => quirks.shift_vx = flag()?,
+                "jump_vx" => quirks.jump_vx = flag()?,
+                "index_overflow_vf" => quirks.index_overflow_vf = flag()?,
+                "horizontal_scroll" => {
+                    quirks.horizontal_scroll = value.parse().ok()
+                        .filter(|distance| (1..=MAX_HORIZONTAL_SCROLL).contains(distance))
+                        .ok_or(format!("Bad value for quirk '{}'", name))?
+                }
+                "load_store" => {
+                    quirks.load_store = value.parse::<usize>().ok()
+                        .and_then(|index| LoadStore::ALL.get(index).copied())
+                        .ok_or(format!("Bad value for quirk '{}'", name))?
+                }
+                _ => (),
+            }
+        }
+        Ok(quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::from_profile(Profile::CosmacVip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_detection() {
+        assert_eq!(Quirks::default().profile(), Some(Profile::CosmacVip));
+        assert_eq!(Quirks::from_profile(Profile::XoChip).profile_name(), "XO-CHIP");
+        assert_eq!(Quirks::from_profile(Profile::Chip48).profile(), Some(Profile::Chip48));
+        assert_eq!(Quirks::from_profile(Profile::SuperChip).profile(), Some(Profile::SuperChip));
+    }
+
+    #[test]
+    fn test_quirks_text_round_trip() {
+        let quirks = Quirks::from_profile(Profile::XoChip);
+        assert_eq!(Quirks::parse_text(&quirks.to_text()).unwrap(), quirks);
+        let quirks = Quirks { wrap_top: true, ..Quirks::default() };
+        assert_eq!(Quirks::parse_text(&quirks.to_text()).unwrap(), quirks);
+        assert_eq!(Quirks::parse_text(&quirks.to_text()).unwrap().profile_name(), "Custom");
+        assert_eq!(Quirks::parse_text("unknown=1").unwrap(), Quirks::default());
+        assert!(Quirks::parse_text("wide_index=2").is_err());
+        let quirks = Quirks { load_store: LoadStore::None, ..Quirks::default() };
+        assert_eq!(Quirks::parse_text(&quirks.to_text()).unwrap(), quirks);
+        assert!(Quirks::parse_text("load_store=3").is_err());
+        let quirks = Quirks { horizontal_scroll: 8, ..Quirks::default() };
+        assert_eq!(Quirks::parse_text(&quirks.to_text()).unwrap(), quirks);
+        assert!(Quirks::parse_text("horizontal_scroll=0").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use log::info;
+use crate::audio::SAMPLE_RATE;
+
+pub const INPUT_HEADER: &str = "CHIP8-INPUT 1";
+
+// Each frame is stored as a 16-bit mask with bit N set while key N is held
+pub fn keys_to_mask(keys: &[bool; 16]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |mask, (key, down)| if *down { mask | 1 << key } else { mask })
+}
+
+pub fn mask_to_keys(mask: u16) -> [bool; 16] {
+    let mut keys = [false; 16];
+    for (key, down) in keys.iter_mut().enumerate() {
+        *down = mask & (1 << key) != 0;
+    }
+    keys
+}
+
+/// Writes the keypad state for every frame to an input file
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    filename: String,
+    pub frames: u64,
+}
+
+impl InputRecorder {
+    pub fn start(filename: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "{}", INPUT_HEADER)?;
+        info!("Recording input to '{}'", filename);
+        Ok(InputRecorder {
+            writer,
+            filename: filename.to_owned(),
+            frames: 0,
+        })
+    }
+
+    pub fn record_frame(&mut self, keys: &[bool; 16]) -> io::Result<()> {
+        self.frames += 1;
+        writeln!(self.writer, "{:04x}", keys_to_mask(keys))
+    }
+
+    // Flushes everything to disk, so the file is complete even if the app is closing
+    pub fn stop(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        info!("Stopped recording input to '{}' after {} frames", self.filename, self.frames);
+        Ok(())
+    }
+}
+
+const WAV_HEADER_LEN: u32 = 44;
+
+// A 16-bit mono PCM WAV header for data_len bytes of samples
+pub fn wav_header(data_len: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN as usize);
+    header.extend(b"RIFF");
+    header.extend((WAV_HEADER_LEN - 8 + data_len).to_le_bytes());
+    header.extend(b"WAVEfmt ");
+    header.extend(16u32.to_le_bytes());
+    header.extend(1u16.to_le_bytes()); // PCM
+    header.extend(1u16.to_le_bytes()); // Mono
+    header.extend(SAMPLE_RATE.to_le_bytes());
+    header.extend((SAMPLE_RATE * 2).to_le_bytes());
+    header.extend(2u16.to_le_bytes());
+    header.extend(16u16.to_le_bytes());
+    header.extend(b"data");
+    header.extend(data_len.to_le_bytes());
+    header
+}
+
+/// Writes the audio output for every frame to a WAV file. Frames are recorded alongside the
+/// input frames, so both stay in step with a screen capture started at the same time.
+pub struct AudioRecorder {
+    writer: BufWriter<File>,
+    filename: String,
+    pub frames: u64,
+    data_len: u32,
+}
+
+impl AudioRecorder {
+    pub fn start(filename: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        // The sizes aren't known until the recording stops, so the header is rewritten then
+        writer.write_all(&wav_header(0))?;
+        info!("Recording audio to '{}'", filename);
+        Ok(AudioRecorder {
+            writer,
+            filename: filename.to_owned(),
+            frames: 0,
+            data_len: 0,
+        })
+    }
+
+    pub fn record_frame(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.frames += 1;
+        for sample in samples {
+            let sample = (sample.clamp(-1., 1.) * i16::MAX as f32) as i16;
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += samples.len() as u32 * 2;
+        Ok(())
+    }
+
+    pub fn stop(mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&wav_header(self.data_len))?;
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        info!("Stopped recording audio to '{}' after {} frames", self.filename, self.frames);
+        // A GIF can't hold sound, so point at a way of pairing the two
+        info!("To add it to a screen capture: ffmpeg -i capture.gif -i '{}' -shortest -pix_fmt yuv420p capture.mp4", self.filename);
+        Ok(())
+    }
+}
+
+pub fn read_input_file(filename: &str) -> Result<Vec<u16>, String> {
+    let text = fs::read_to_string(filename).map_err(|e| e.to_string())?;
+    parse_input(&text)
+}
+
+pub fn parse_input(text: &str) -> Result<Vec<u16>, String> {
+    let mut lines = text.lines();
+    if lines.next() != Some(INPUT_HEADER) {
+        return Err("Not a CHIP-8 input recording".to_owned());
+    }
+    lines
+        .map(|line| u16::from_str_radix(line.trim(), 16).map_err(|_| format!("Bad input frame '{}'", line)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_mask() {
+        let mut keys = [false; 16];
+        keys[0x0] = true;
+        keys[0xA] = true;
+        assert_eq!(keys_to_mask(&keys), 0x0401);
+        assert_eq!(mask_to_keys(0x0401), keys);
+    }
+
+    #[test]
+    fn test_stop_recording_writes_valid_file() {
+        let filename = std::env::temp_dir().join("chip8_test_recording.txt");
+        let filename = filename.to_str().unwrap();
+        let mut keys = [false; 16];
+        let mut recorder = InputRecorder::start(filename).unwrap();
+        recorder.record_frame(&keys).unwrap();
+        keys[0x5] = true;
+        recorder.record_frame(&keys).unwrap();
+        recorder.record_frame(&keys).unwrap();
+        recorder.stop().unwrap();
+        assert_eq!(read_input_file(filename).unwrap(), vec![0x0000, 0x0020, 0x0020]);
+        fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_audio_recording_writes_valid_wav() {
+        let filename = std::env::temp_dir().join("chip8_test_recording.wav");
+        let filename = filename.to_str().unwrap();
+        let mut recorder = AudioRecorder::start(filename).unwrap();
+        recorder.record_frame(&[0., 0.25, -0.25]).unwrap();
+        recorder.record_frame(&[1., -1.]).unwrap();
+        recorder.stop().unwrap();
+        let bytes = fs::read(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let i16_at = |i: usize| i16::from_le_bytes(bytes[i..i + 2].try_into().unwrap());
+        assert_eq!(bytes.len(), 44 + 5 * 2);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(4) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(24), SAMPLE_RATE);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(40), 10);
+        assert_eq!([i16_at(44), i16_at(46), i16_at(48), i16_at(50), i16_at(52)], [0, 8191, -8191, i16::MAX, -i16::MAX]);
+    }
+}
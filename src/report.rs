@@ -0,0 +1,190 @@
+use std::fs;
+use log::{info, warn};
+use crate::chip8::{Chip8, Snapshot};
+use crate::chip8::quirks::Quirks;
+
+const HEADER: &str = "CHIP8-REPORT 1";
+
+/// Everything needed to reproduce a bug, written to a single text file
+pub struct Report {
+    pub rom: Vec<u8>,
+    pub rom_hash: u64,
+    pub state: Snapshot,
+    pub quirks: Quirks,
+    pub seed: u64,
+    pub pc_history: Vec<usize>,
+    pub log: Vec<String>,
+    pub speed: u32,
+}
+
+impl Report {
+    pub fn capture(chip8: &Chip8, speed: u32, log: Vec<String>) -> Self {
+        Report {
+            rom: chip8.rom.clone(),
+            rom_hash: chip8.rom_hash(),
+            state: chip8.snapshot(),
+            quirks: chip8.quirks,
+            seed: chip8.seed,
+            pc_history: chip8.pc_history.iter().copied().collect(),
+            log,
+            speed,
+        }
+    }
+
+    pub fn restore(&self, chip8: &mut Chip8) {
+        chip8.reset();
+        chip8.seed_rng(self.seed);
+        chip8.rom = self.rom.clone();
+        chip8.restore(&self.state);
+        chip8.quirks = self.quirks;
+        chip8.pc_history = self.pc_history.iter().copied().collect();
+    }
+
+    pub fn save(&self, filename: &str) -> std::io::Result<()> {
+        fs::write(filename, self.to_text())?;
+        info!("Saved bug report to '{}'", filename);
+        Ok(())
+    }
+
+    pub fn load(filename: &str) -> Result<Report, String> {
+        let text = fs::read_to_string(filename).map_err(|e| e.to_string())?;
+        Report::parse(&text)
+    }
+
+    pub fn to_text(&self) -> String {
+        let state = &self.state;
+        let mut lines = vec![
+            HEADER.to_owned(),
+            format!("rom_hash {:016x}", self.rom_hash),
+            format!("speed {}", self.speed),
+            format!("quirks {}", self.quirks.to_text()),
+            format!("seed {:016x}", self.seed),
+            format!("pc {:03x}", state.pc),
+            format!("reg_i {:03x}", state.reg_i),
+            format!("delay_timer {}", state.delay_timer),
+            format!("sound_timer {}", state.sound_timer),
+            format!("reg {}", to_hex(&state.reg)),
+            format!("stack {}", join_hex(&state.stack.iter().map(|e| *e as usize).collect::<Vec<_>>())),
+            format!("pc_history {}", join_hex(&self.pc_history)),
+            format!("rom {}", to_hex(&self.rom)),
+            format!("memory {}", to_hex(&state.memory)),
+            format!("hires {}", state.hires as u8),
+            format!("display {}", state.display.iter().map(|p| if *p { '1' } else { '0' }).collect::<String>()),
+        ];
+        for message in &self.log {
+            lines.push(format!("log {}", message));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn parse(text: &str) -> Result<Report, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("Not a CHIP-8 bug report".to_owned());
+        }
+        let mut chip8 = Chip8::new();
+        let mut report = Report::capture(&chip8, 0, Vec::new());
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let state = &mut report.state;
+            match key {
+                "rom_hash" => report.rom_hash = parse_hex(value)? as u64,
+                "speed" => report.speed = value.parse().map_err(|_| format!("Bad speed '{}'", value))?,
+                "quirks" => report.quirks = Quirks::parse_text(value)?,
+                "seed" => report.seed = u64::from_str_radix(value, 16).map_err(|_| format!("Bad seed '{}'", value))?,
+                "pc" => state.pc = parse_hex(value)?,
+                "reg_i" => state.reg_i = parse_hex(value)? as u16,
+                "delay_timer" => state.delay_timer = value.parse().map_err(|_| format!("Bad delay timer '{}'", value))?,
+                "sound_timer" => state.sound_timer = value.parse().map_err(|_| format!("Bad sound timer '{}'", value))?,
+                "reg" => copy_exact(&mut state.reg, &from_hex(value)?, "reg")?,
+                "stack" => state.stack = split_hex(value)?.iter().map(|e| *e as u16).collect(),
+                "pc_history" => report.pc_history = split_hex(value)?,
+                "rom" => report.rom = from_hex(value)?,
+                "memory" => copy_exact(&mut state.memory, &from_hex(value)?, "memory")?,
+                "hires" => state.hires = value == "1",
+                "display" => {
+                    let pixels: Vec<bool> = value.chars().map(|c| c == '1').collect();
+                    copy_exact(&mut state.display, &pixels, "display")?;
+                }
+                "log" => report.log.push(value.to_owned()),
+                _ => warn!("Ignoring unknown bug report field '{}'", key),
+            }
+        }
+        chip8.rom = report.rom.clone();
+        if chip8.rom_hash() != report.rom_hash {
+            warn!("Bug report ROM hash doesn't match the ROM it contains");
+        }
+        Ok(report)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() % 2 == 1 {
+        return Err("Hex data has an odd length".to_owned());
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("Bad hex byte '{}'", &text[i..i + 2])))
+        .collect()
+}
+
+fn join_hex(values: &[usize]) -> String {
+    values.iter().map(|v| format!("{:03x}", v)).collect::<Vec<_>>().join(" ")
+}
+
+fn split_hex(text: &str) -> Result<Vec<usize>, String> {
+    text.split_whitespace().map(parse_hex).collect()
+}
+
+fn parse_hex(text: &str) -> Result<usize, String> {
+    usize::from_str_radix(text, 16).map_err(|_| format!("Bad hex value '{}'", text))
+}
+
+fn copy_exact<T: Copy>(dest: &mut [T], src: &[T], field: &str) -> Result<(), String> {
+    if dest.len() != src.len() {
+        return Err(format!("Field '{}' has the wrong length", field));
+    }
+    dest.copy_from_slice(src);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_round_trip() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.wide_index = true;
+        chip8.load_vec(vec![0x00FF, 0x6A45, 0xA123, 0x220A, 0x0000, 0xD003]).unwrap();
+        for _ in 0..5 {
+            chip8.tick(None);
+        }
+        let report = Report::capture(&chip8, 12, vec!["[WARN] something odd".to_owned()]);
+        let parsed = Report::parse(&report.to_text()).unwrap();
+        assert_eq!(parsed.rom, chip8.rom);
+        assert_eq!(parsed.rom_hash, chip8.rom_hash());
+        assert_eq!(parsed.state, report.state);
+        assert_eq!(parsed.quirks, chip8.quirks);
+        assert_eq!(parsed.seed, chip8.seed);
+        assert_eq!(parsed.pc_history, vec![0x200, 0x202, 0x204, 0x206, 0x20A]);
+        assert_eq!(parsed.log, report.log);
+        assert_eq!(parsed.speed, 12);
+
+        let mut restored = Chip8::new();
+        parsed.restore(&mut restored);
+        assert_eq!(restored.snapshot(), chip8.snapshot());
+        assert_eq!(restored.rom_hash(), chip8.rom_hash());
+        assert_eq!(restored.pc_history, chip8.pc_history);
+        assert_eq!(restored.quirks, chip8.quirks);
+    }
+
+    #[test]
+    fn test_report_rejects_other_files() {
+        assert!(Report::parse("hello").is_err());
+    }
+}
@@ -1,51 +1,53 @@
-use std::process;
+#[derive(Debug, PartialEq)]
+pub enum StackError {
+    Overflow,
+    Underflow,
+}
+
+pub const CAPACITY: usize = 32;
 
 pub struct Stack {
-    stack: [u16;32],
+    stack: [u16;CAPACITY],
     top: i8,
 }
 
 impl Stack {
     pub fn new() -> Self {
         Stack {
-            stack: [0;32],
+            stack: [0;CAPACITY],
             top: -1,
         }
     }
-    pub fn push(&mut self, element: u16) {
-        if self.is_full() == false {
-            self.top += 1;
-            self.stack[self.top as usize] = element;
-        } else {
-            eprintln!("Error: stack is full and cannot push");
-            process::exit(1);
+    pub fn push(&mut self, element: u16) -> Result<(), StackError> {
+        if self.is_full() {
+            return Err(StackError::Overflow);
         }
+        self.top += 1;
+        self.stack[self.top as usize] = element;
+        Ok(())
     }
-    pub fn pop(&mut self) -> u16 {
-        let element: u16;
-
-        if self.is_empty() == false {
-            element = self.stack[self.top as usize];
-            self.top -= 1;
-            element
-        } else {
-            eprintln!("Error: stack is empty and cannot pop");
-            process::exit(1);
+    pub fn pop(&mut self) -> Result<u16, StackError> {
+        if self.is_empty() {
+            return Err(StackError::Underflow);
         }
+        let element = self.stack[self.top as usize];
+        self.top -= 1;
+        Ok(element)
     }
-    pub fn top(&self) -> u16 {
-        if self.is_empty() == false {
-            self.stack[self.top as usize]
-        } else {
-            eprintln!("Error: stack is empty and cannot pop");
-            process::exit(1);
+    pub fn top(&self) -> Option<u16> {
+        if self.is_empty() {
+            return None;
         }
+        Some(self.stack[self.top as usize])
+    }
+    pub fn elements(&self) -> &[u16] {
+        &self.stack[..(self.top + 1) as usize]
+    }
+    pub fn len(&self) -> usize {
+        (self.top + 1) as usize
     }
     pub fn is_full(&self) -> bool {
-        match self.top {
-            31 => true,
-            _ => false,
-        }
+        self.len() == CAPACITY
     }
     pub fn is_empty(&self) -> bool {
         match self.top {
@@ -55,6 +57,12 @@ impl Stack {
     }
 }
 
+impl Default for Stack {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -62,15 +70,16 @@ mod test {
     #[test]
     fn stack_push_pop() {
         let mut stack = Stack::new();
-        stack.push(5);
-        stack.push(7);
+        stack.push(5).unwrap();
+        stack.push(7).unwrap();
         assert_eq!(stack.stack[0], 5);
         assert_eq!(stack.stack[1], 7);
-        assert_eq!(stack.pop(), 7);
-        assert_eq!(stack.pop(), 5);
+        assert_eq!(stack.pop(), Ok(7));
+        assert_eq!(stack.pop(), Ok(5));
         assert_eq!(stack.top, -1);
         assert_eq!(stack.is_full(), false);
         assert_eq!(stack.is_empty(), true);
+        assert_eq!(stack.len(), 0);
     }
 
     #[test]
@@ -83,10 +92,23 @@ mod test {
     fn stack_is_full() {
         let mut stack = Stack::new();
         for idx in 0..32 {
-            stack.push(idx);
+            stack.push(idx).unwrap();
         }
         assert_eq!(stack.is_full(), true);
-        stack.pop();
+        assert_eq!(stack.len(), CAPACITY);
+        stack.pop().unwrap();
         assert_eq!(stack.is_full(), false);
     }
+
+    #[test]
+    fn stack_overflow_underflow() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.pop(), Err(StackError::Underflow));
+        assert_eq!(stack.top(), None);
+        for idx in 0..32 {
+            stack.push(idx).unwrap();
+        }
+        assert_eq!(stack.push(32), Err(StackError::Overflow));
+        assert_eq!(stack.top(), Some(31));
+    }
 }
\ No newline at end of file
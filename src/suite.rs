@@ -0,0 +1,139 @@
+// Runs every ROM in a directory headless and writes a text report of how each one ended,
+// with its final display, so a whole test suite can be checked at a glance.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::time::Duration;
+use log::info;
+use crate::chip8::{Chip8, RunOutcome};
+use crate::chip8::error::Chip8Error;
+
+pub const DEFAULT_CYCLES: u64 = 10_000;
+pub const REPORT_FILE: &str = "chip8_suite_report.txt";
+// Wall-clock limit for each ROM, on top of the cycle count
+const TIME_LIMIT: Duration = Duration::from_secs(10);
+
+pub enum Outcome {
+    // Ran all its cycles, or halted in an idle loop before then
+    Finished { instructions: u64, halted: bool, display: String },
+    TimedOut { instructions: u64 },
+    Panicked(String),
+}
+
+pub struct SuiteResult {
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+pub fn run_rom(rom: &[u8], cycles: u64, time_limit: Duration) -> Outcome {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut chip8 = Chip8::new();
+        chip8.seed_rng(0);
+        chip8.load_bytes(rom)?;
+        Ok::<_, Chip8Error>((chip8.run_bounded(cycles, Some(time_limit)), chip8.display_to_string()))
+    }));
+    match result {
+        Ok(Err(e)) => Outcome::Panicked(format!("Unable to load ROM: {}", e)),
+        Ok(Ok((RunOutcome::Halted { instructions }, display))) => Outcome::Finished { instructions, halted: true, display },
+        Ok(Ok((RunOutcome::TimedOut { instructions }, display))) if instructions == cycles => {
+            Outcome::Finished { instructions, halted: false, display }
+        }
+        Ok(Ok((RunOutcome::TimedOut { instructions }, _))) => Outcome::TimedOut { instructions },
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+            Outcome::Panicked(message)
+        }
+    }
+}
+
+// Every .ch8 file in the directory, in name order
+pub fn run_suite(dir: &Path, cycles: u64) -> std::io::Result<Vec<SuiteResult>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ch8"))
+        .collect();
+    paths.sort();
+    let mut results = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        info!("Running '{}'", name);
+        let outcome = match fs::read(&path) {
+            Ok(rom) => run_rom(&rom, cycles, TIME_LIMIT),
+            Err(e) => Outcome::Panicked(format!("Unable to read ROM: {}", e)),
+        };
+        results.push(SuiteResult { name, outcome });
+    }
+    Ok(results)
+}
+
+pub fn report_text(results: &[SuiteResult]) -> String {
+    let mut lines = Vec::new();
+    let mut failed = Vec::new();
+    for result in results {
+        match &result.outcome {
+            Outcome::Finished { instructions, halted, display } => {
+                let ending = if *halted { "halted" } else { "ran" };
+                lines.push(format!("== {} ({} after {} instructions)", result.name, ending, instructions));
+                lines.push(display.clone());
+                lines.push(String::new());
+            }
+            Outcome::TimedOut { instructions } => {
+                failed.push(format!("{}: timed out after {} instructions", result.name, instructions));
+            }
+            Outcome::Panicked(message) => failed.push(format!("{}: panicked: {}", result.name, message)),
+        }
+    }
+    let finished = results.len() - failed.len();
+    lines.insert(0, format!("{} ROMs, {} finished, {} failed\n", results.len(), finished, failed.len()));
+    if !failed.is_empty() {
+        lines.push("== Failed".to_owned());
+        lines.extend(failed);
+    }
+    lines.join("\n") + "\n"
+}
+
+// Entry point for --test-suite, returning the process exit code
+pub fn main(dir: &str, cycles: u64) -> i32 {
+    let results = match run_suite(Path::new(dir), cycles) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Unable to read '{}': {}", dir, e);
+            return 1;
+        }
+    };
+    let report = report_text(&results);
+    if let Err(e) = fs::write(REPORT_FILE, &report) {
+        eprintln!("Unable to write '{}': {}", REPORT_FILE, e);
+        return 1;
+    }
+    println!("{}", report.lines().next().unwrap());
+    println!("Wrote '{}'", REPORT_FILE);
+    let failed = results.iter().any(|result| !matches!(result.outcome, Outcome::Finished { .. }));
+    if failed { 2 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suite_report() {
+        let results = vec![
+            SuiteResult { name: "halts.ch8".to_owned(), outcome: run_rom(&[0x12, 0x00], 100, TIME_LIMIT) },
+            SuiteResult { name: "loops.ch8".to_owned(), outcome: run_rom(&[0x70, 0x01, 0x12, 0x00], 100, TIME_LIMIT) },
+            SuiteResult { name: "slow.ch8".to_owned(), outcome: run_rom(&[0x70, 0x01, 0x12, 0x00], 100, Duration::ZERO) },
+        ];
+        assert!(matches!(results[0].outcome, Outcome::Finished { instructions: 1, halted: true, .. }));
+        assert!(matches!(results[1].outcome, Outcome::Finished { instructions: 100, halted: false, .. }));
+        assert!(matches!(results[2].outcome, Outcome::TimedOut { instructions: 0 }));
+        let report = report_text(&results);
+        assert!(report.starts_with("3 ROMs, 2 finished, 1 failed\n"));
+        assert!(report.contains("== halts.ch8 (halted after 1 instructions)\n....."));
+        assert!(report.ends_with("== Failed\nslow.ch8: timed out after 0 instructions\n"));
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use crate::chip8::{Chip8, Snapshot};
+use crate::recording::keys_to_mask;
+
+// Ten seconds of 60Hz frames
+pub const TIMELINE_FRAMES: usize = 600;
+
+/// What happened in one 60Hz frame, with the state at its end to jump back to
+pub struct TimelineFrame {
+    pub frame: u64,
+    pub drew: bool,
+    pub sound: bool,
+    // Keys held, as a mask with bit N set for key N
+    pub keys: u16,
+    pub snapshot: Snapshot,
+}
+
+/// The last TIMELINE_FRAMES frames, oldest first
+pub struct Timeline {
+    pub frames: VecDeque<TimelineFrame>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline { frames: VecDeque::with_capacity(TIMELINE_FRAMES) }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    // Records the frame that has just finished, dropping the oldest once full
+    pub fn record(&mut self, frame: u64, drew: bool, chip8: &Chip8) {
+        if self.frames.len() == TIMELINE_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(TimelineFrame {
+            frame,
+            drew,
+            sound: chip8.sound_timer > 0,
+            keys: keys_to_mask(&chip8.keys),
+            snapshot: chip8.snapshot(),
+        });
+    }
+
+    // Restores the state at the end of a recorded frame, forgetting the frames after it since
+    // running on from there replaces them. Returns the frame number jumped to.
+    pub fn jump_to(&mut self, index: usize, chip8: &mut Chip8) -> Option<u64> {
+        let entry = self.frames.get(index)?;
+        chip8.restore(&entry.snapshot);
+        let frame = entry.frame;
+        self.frames.truncate(index + 1);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_jump() {
+        let mut chip8 = Chip8::new();
+        let mut timeline = Timeline::new();
+        chip8.load_vec(vec![0x7001, 0x1200]).unwrap();
+        for frame in 0..TIMELINE_FRAMES as u64 + 5 {
+            chip8.keys[5] = frame == 3;
+            chip8.run_frame(&chip8.keys.clone(), 2);
+            timeline.record(frame, frame == 0, &chip8);
+        }
+        assert_eq!(timeline.frames.len(), TIMELINE_FRAMES);
+        assert_eq!(timeline.frames[0].frame, 5);
+
+        timeline.clear();
+        chip8.reg[0] = 0;
+        for frame in 0..5 {
+            chip8.keys[5] = frame == 3;
+            chip8.run_frame(&chip8.keys.clone(), 2);
+            timeline.record(frame, frame == 0, &chip8);
+        }
+        assert!(timeline.frames[0].drew && !timeline.frames[1].drew);
+        assert_eq!(timeline.frames[3].keys, 1 << 5);
+        assert_eq!(timeline.jump_to(2, &mut chip8), Some(2));
+        assert_eq!(chip8.reg[0], 3);
+        assert_eq!(timeline.frames.len(), 3);
+        assert_eq!(timeline.jump_to(5, &mut chip8), None);
+    }
+}
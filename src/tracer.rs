@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use log::info;
+use crate::chip8::{opcodes, Chip8};
+
+/// Writes a line for every instruction run to a trace file, optionally followed by the
+/// registers and display after each draw or clear
+pub struct Tracer {
+    writer: BufWriter<File>,
+    filename: String,
+    pub draw_snapshots: bool,
+    pub instructions: u64,
+}
+
+impl Tracer {
+    pub fn start(filename: &str, draw_snapshots: bool) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(filename)?);
+        info!("Tracing to '{}'", filename);
+        Ok(Tracer {
+            writer,
+            filename: filename.to_owned(),
+            draw_snapshots,
+            instructions: 0,
+        })
+    }
+
+    // Records the instruction the last tick ran
+    pub fn record(&mut self, chip8: &Chip8) -> io::Result<()> {
+        let pc = match chip8.pc_history.back() {
+            Some(pc) => *pc,
+            None => return Ok(()),
+        };
+        let code = chip8.opcode.code;
+        self.instructions += 1;
+        writeln!(self.writer, "{:03X} {:04X} {}", pc, code, opcodes::disassemble(code))?;
+        if self.draw_snapshots && (code & 0xF000 == 0xD000 || code == 0x00E0) {
+            let reg: Vec<String> = chip8.reg.iter().map(|value| format!("{:02X}", value)).collect();
+            writeln!(self.writer, "    V0-VF {} I {:03X}", reg.join(" "), chip8.reg_i)?;
+            for line in chip8.display_to_string().lines() {
+                writeln!(self.writer, "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn stop(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        info!("Stopped tracing to '{}' after {} instructions", self.filename, self.instructions);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_draw_snapshot() {
+        let filename = std::env::temp_dir().join("chip8_test_trace.txt");
+        let filename = filename.to_str().unwrap();
+        let mut tracer = Tracer::start(filename, true).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_vec(vec![0x6105, 0xD001]).unwrap();
+        for _ in 0..2 {
+            chip8.tick(None);
+            tracer.record(&chip8).unwrap();
+        }
+        tracer.stop().unwrap();
+        let text = std::fs::read_to_string(filename).unwrap();
+        std::fs::remove_file(filename).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3 + 32);
+        assert_eq!(lines[0], "200 6105 LD V1, 0x05");
+        assert!(lines[1].starts_with("202 D001 "));
+        assert_eq!(lines[2], "    V0-VF 00 05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I 000");
+        // The first font byte, F0, drawn in the top left
+        assert_eq!(lines[3], format!("    ####{}", ".".repeat(60)));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+use log::info;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// How long the file has to stay unchanged before it's reloaded, so a build that writes in steps is seen once
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Notices when a file is rewritten by polling its modification time
+pub struct FileWatcher {
+    pub path: String,
+    modified: Option<SystemTime>,
+    changed_at: Option<Instant>,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        FileWatcher {
+            path: path.to_owned(),
+            modified: modified_time(path),
+            changed_at: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    // Returns true once the file has changed and then settled
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        if now.duration_since(self.last_poll) < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = now;
+        self.check(now, modified_time(&self.path))
+    }
+
+    fn check(&mut self, now: Instant, modified: Option<SystemTime>) -> bool {
+        if modified != self.modified {
+            if modified.is_none() {
+                info!("'{}' is missing, waiting for it to come back", self.path);
+            }
+            self.modified = modified;
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            // A missing file might be mid-write, so keep waiting rather than reloading
+            Some(changed_at) if modified.is_some() && now.duration_since(changed_at) >= DEBOUNCE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher() -> FileWatcher {
+        FileWatcher {
+            path: "test.ch8".to_owned(),
+            modified: Some(SystemTime::UNIX_EPOCH),
+            changed_at: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_watcher_debounces_changes() {
+        let mut watcher = watcher();
+        let start = Instant::now();
+        let first = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        let second = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2));
+        assert!(!watcher.check(start, first));
+        assert!(!watcher.check(start + Duration::from_millis(250), second));
+        assert!(!watcher.check(start + Duration::from_millis(500), second));
+        assert!(watcher.check(start + Duration::from_millis(750), second));
+        assert!(!watcher.check(start + Duration::from_millis(1000), second));
+    }
+
+    #[test]
+    fn test_watcher_waits_for_missing_file() {
+        let mut watcher = watcher();
+        let start = Instant::now();
+        let rewritten = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        assert!(!watcher.check(start, None));
+        assert!(!watcher.check(start + Duration::from_secs(1), None));
+        assert!(!watcher.check(start + Duration::from_secs(2), rewritten));
+        assert!(watcher.check(start + Duration::from_secs(3), rewritten));
+    }
+}
@@ -0,0 +1,128 @@
+// The window's position on screen, which macroquad can't read or set. On Linux it's read and moved through
+// the X11 window sapp-linux opened; elsewhere there's no position, so only the window size is kept.
+
+// Parses a saved "X,Y" window position
+pub fn parse_position(text: &str) -> Option<(i32, i32)> {
+    let (x, y) = text.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+// Keeps a window of this size wholly on a screen of this size, or at its top left if it's too big to fit,
+// so a window saved on a monitor that's since gone doesn't reopen out of sight
+pub fn clamp_position((x, y): (i32, i32), (width, height): (i32, i32), (screen_width, screen_height): (i32, i32)) -> (i32, i32) {
+    (x.min(screen_width - width).max(0), y.min(screen_height - height).max(0))
+}
+
+// Moves the window back to a saved position, kept on screen
+pub fn restore_position(saved: Option<&str>) {
+    let Some(position) = saved.and_then(parse_position) else {
+        return;
+    };
+    if let (Some(size), Some(screen)) = (platform::window_size(), platform::screen_size()) {
+        platform::move_to(clamp_position(position, size, screen));
+    }
+}
+
+pub use platform::position;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::os::raw::{c_int, c_uint, c_ulong, c_void};
+
+    #[link(name = "X11")]
+    extern "C" {
+        fn XGetGeometry(display: *mut c_void, drawable: c_ulong, root: *mut c_ulong, x: *mut c_int, y: *mut c_int,
+                        width: *mut c_uint, height: *mut c_uint, border: *mut c_uint, depth: *mut c_uint) -> c_int;
+        fn XTranslateCoordinates(display: *mut c_void, src: c_ulong, dest: c_ulong, src_x: c_int, src_y: c_int,
+                                 dest_x: *mut c_int, dest_y: *mut c_int, child: *mut c_ulong) -> c_int;
+        fn XMoveWindow(display: *mut c_void, window: c_ulong, x: c_int, y: c_int) -> c_int;
+        fn XFlush(display: *mut c_void) -> c_int;
+    }
+
+    // The display connection and window sapp-linux opened, once there is a window
+    fn window() -> Option<(*mut c_void, c_ulong)> {
+        let (display, window) = unsafe { (sapp_linux::_sapp_x11_display as *mut c_void, sapp_linux::_sapp_x11_window) };
+        (!display.is_null() && window != 0).then_some((display, window))
+    }
+
+    // Position relative to the parent, and size, of a window
+    fn geometry(display: *mut c_void, drawable: c_ulong) -> Option<(i32, i32, i32, i32)> {
+        let (mut root, mut x, mut y, mut width, mut height, mut border, mut depth) = (0, 0, 0, 0, 0, 0, 0);
+        let found = unsafe { XGetGeometry(display, drawable, &mut root, &mut x, &mut y, &mut width, &mut height, &mut border, &mut depth) };
+        (found != 0).then_some((x, y, width as i32, height as i32))
+    }
+
+    // Top left of the window's frame on the screen. The window manager puts the window inside a frame,
+    // and XMoveWindow places the frame, so saving the window's own corner would creep on every restore.
+    pub fn position() -> Option<(i32, i32)> {
+        let (display, window) = window()?;
+        let root = unsafe { sapp_linux::_sapp_x11_root };
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        if unsafe { XTranslateCoordinates(display, window, root, 0, 0, &mut x, &mut y, &mut child) } == 0 {
+            return None;
+        }
+        let (offset_x, offset_y, _, _) = geometry(display, window)?;
+        Some((x - offset_x, y - offset_y))
+    }
+
+    pub fn window_size() -> Option<(i32, i32)> {
+        let (display, window) = window()?;
+        geometry(display, window).map(|(_, _, width, height)| (width, height))
+    }
+
+    // The root window, which spans every monitor
+    pub fn screen_size() -> Option<(i32, i32)> {
+        let (display, _) = window()?;
+        geometry(display, unsafe { sapp_linux::_sapp_x11_root }).map(|(_, _, width, height)| (width, height))
+    }
+
+    pub fn move_to((x, y): (i32, i32)) {
+        if let Some((display, window)) = window() {
+            unsafe {
+                XMoveWindow(display, window, x, y);
+                XFlush(display);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    pub fn position() -> Option<(i32, i32)> {
+        None
+    }
+
+    pub fn window_size() -> Option<(i32, i32)> {
+        None
+    }
+
+    pub fn screen_size() -> Option<(i32, i32)> {
+        None
+    }
+
+    pub fn move_to(_position: (i32, i32)) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("100,200"), Some((100, 200)));
+        assert_eq!(parse_position(" -8, 30 "), Some((-8, 30)));
+        assert_eq!(parse_position("100"), None);
+        assert_eq!(parse_position("left,top"), None);
+    }
+
+    #[test]
+    fn test_clamp_position() {
+        let screen = (1920, 1080);
+        assert_eq!(clamp_position((100, 200), (960, 600), screen), (100, 200));
+        // Off the right or bottom, as after unplugging a second monitor
+        assert_eq!(clamp_position((3000, 900), (960, 600), screen), (960, 480));
+        assert_eq!(clamp_position((-50, -20), (960, 600), screen), (0, 0));
+        // Too big to fit keeps its top left corner visible
+        assert_eq!(clamp_position((500, 500), (2560, 1440), screen), (0, 0));
+    }
+}